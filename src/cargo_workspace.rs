@@ -0,0 +1,567 @@
+//! Version sync for a whole Cargo workspace
+//!
+//! Starting from the root manifest, the shared `[workspace.package]` version, every member crate
+//! released with it, the version requirements of path dependencies on those members and their
+//! `Cargo.lock` entries are all set to the new version. A member follows the shared version when it
+//! inherits it with `version.workspace = true` or holds the same version, members versioned on their
+//! own are left as they are. Cargo.lock is edited in place instead of
+//! running cargo, so no network access is needed.
+use std::collections::HashSet;
+use std::path::Path;
+
+use toml_edit::{Document, Item, TableLike};
+
+use crate::util::glob_directories;
+
+/// The tables that list dependencies, either at the root of a manifest or under `[target.<cfg>]`
+pub(crate) const DEPENDENCY_TABLES: [&str; 3] =
+    ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Reads the version the workspace shares, `[workspace.package]` first then the root `[package]`
+/// # Arguments
+/// * `file` - The contents of the root Cargo.toml
+/// # Returns
+/// * The version, or the reason it could not be read
+pub(crate) fn read_workspace_version(file: &str) -> Result<String, String> {
+    let toml = toml::from_str::<toml::Value>(file).map_err(|err| err.to_string())?;
+    let workspace = toml
+        .get("workspace")
+        .ok_or_else(|| "missing [workspace] table".to_string())?;
+    workspace
+        .get("package")
+        .and_then(|package| package.get("version"))
+        .or_else(|| {
+            toml.get("package")
+                .and_then(|package| package.get("version"))
+        })
+        .and_then(|version| version.as_str())
+        .map(|version| version.to_string())
+        .ok_or_else(|| "missing workspace.package.version or package.version".to_string())
+}
+
+/// Reads the version a manifest declares for its own package, `version.workspace = true` is not one
+/// # Arguments
+/// * `file` - The contents of the Cargo.toml
+pub(crate) fn read_package_version(file: &str) -> Option<String> {
+    let toml = toml::from_str::<toml::Value>(file).ok()?;
+    toml.get("package")?
+        .get("version")?
+        .as_str()
+        .map(|version| version.to_string())
+}
+
+/// Computes the new contents of every manifest of the workspace and its Cargo.lock
+/// # Arguments
+/// * `manifest_path` - The path of the root Cargo.toml e.g. `./Cargo.toml`
+/// * `version` - The new version
+/// * `read` - Reads the current contents of a file, including contents already planned
+/// # Returns
+/// * The paths and new contents of the files that changed, or the reason the workspace could not be updated
+pub(crate) fn plan_cargo_workspace_sync(
+    manifest_path: &str,
+    version: &str,
+    read: &dyn Fn(&str) -> Result<String, String>,
+) -> Result<Vec<(String, String)>, String> {
+    let root_dir = Path::new(manifest_path)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut root = parse(manifest_path, &read(manifest_path)?)?;
+    let workspace = root
+        .get("workspace")
+        .and_then(|workspace| workspace.as_table_like())
+        .ok_or_else(|| "missing [workspace] table".to_string())?;
+    let member_paths = member_manifests(root_dir, workspace)?;
+
+    let old_version = shared_version(&root);
+    let shares_version = match root
+        .get_mut("workspace")
+        .and_then(|workspace| workspace.get_mut("package"))
+        .and_then(|package| package.get_mut("version"))
+    {
+        Some(item) => set_string(item, version),
+        None => false,
+    };
+    // crates whose version is now the new version, so their requirements and lock entries follow it
+    let mut bumped = HashSet::new();
+    if let Some(name) = set_package_version(&mut root, version, &old_version, shares_version) {
+        bumped.insert(name);
+    }
+    let mut members = vec![];
+    for member_path in member_paths {
+        let mut member = parse(&member_path, &read(&member_path)?)?;
+        if let Some(name) = set_package_version(&mut member, version, &old_version, shares_version)
+        {
+            bumped.insert(name);
+        }
+        members.push((member_path, member));
+    }
+
+    if let Some(dependencies) = root
+        .get_mut("workspace")
+        .and_then(|workspace| workspace.get_mut("dependencies"))
+        .and_then(|dependencies| dependencies.as_table_like_mut())
+    {
+        set_requirements(dependencies, &bumped, version);
+    }
+    let mut planned = vec![];
+    for (path, mut document) in std::iter::once((manifest_path.to_string(), root)).chain(members) {
+        set_dependency_requirements(&mut document, &bumped, version);
+        planned.push((path, document.to_string()));
+    }
+
+    let lock_path = root_dir.join("Cargo.lock").to_string_lossy().to_string();
+    if Path::new(&lock_path).exists() {
+        let mut lock = parse(&lock_path, &read(&lock_path)?)?;
+        if let Some(packages) = lock
+            .get_mut("package")
+            .and_then(|packages| packages.as_array_of_tables_mut())
+        {
+            for package in packages.iter_mut() {
+                let is_member = package
+                    .get("name")
+                    .and_then(|name| name.as_str())
+                    .is_some_and(|name| bumped.contains(name));
+                // registry and git packages with the same name are not part of the workspace
+                if is_member && package.get("source").is_none() {
+                    if let Some(item) = package.get_mut("version") {
+                        set_string(item, version);
+                    }
+                }
+            }
+        }
+        planned.push((lock_path, lock.to_string()));
+    }
+
+    Ok(planned)
+}
+
+/// Reads every version the workspace releases together: the shared version, the version of the root
+/// package and each member following it, and the Cargo.lock entries of those crates
+/// # Arguments
+/// * `manifest_path` - The path of the root Cargo.toml e.g. `./Cargo.toml`
+/// * `read` - Reads the current contents of a file
+/// # Returns
+/// * The path, the key and the version of each, members inheriting the shared version hold it under `package.version.workspace`
+pub(crate) fn read_cargo_workspace_versions(
+    manifest_path: &str,
+    read: &dyn Fn(&str) -> Result<String, String>,
+) -> Result<Vec<(String, String, String)>, String> {
+    let root_dir = Path::new(manifest_path)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let root = parse(manifest_path, &read(manifest_path)?)?;
+    let workspace = root
+        .get("workspace")
+        .and_then(|workspace| workspace.as_table_like())
+        .ok_or_else(|| "missing [workspace] table".to_string())?;
+    let shared = shared_version(&root);
+
+    let mut versions = vec![];
+    let declared = workspace
+        .get("package")
+        .and_then(|package| package.get("version"))
+        .and_then(|version| version.as_str());
+    if let Some(declared) = declared {
+        versions.push((
+            manifest_path.to_string(),
+            "workspace.package.version".to_string(),
+            declared.to_string(),
+        ));
+    }
+    let mut crates = vec![];
+    let mut manifests = vec![(manifest_path.to_string(), root.clone())];
+    for member_path in member_manifests(root_dir, workspace)? {
+        let member = parse(&member_path, &read(&member_path)?)?;
+        manifests.push((member_path, member));
+    }
+    for (path, manifest) in manifests {
+        let Some(package) = manifest.get("package") else {
+            continue;
+        };
+        let Some(name) = package.get("name").and_then(|name| name.as_str()) else {
+            continue;
+        };
+        let version = package.get("version");
+        let inherits = version
+            .and_then(|version| version.get("workspace"))
+            .and_then(|workspace| workspace.as_bool())
+            .unwrap_or(false);
+        match (version.and_then(|version| version.as_str()), &shared) {
+            (Some(version), Some(shared)) if version == shared => {
+                versions.push((path, "package.version".to_string(), version.to_string()))
+            }
+            (None, Some(shared)) if inherits => versions.push((
+                path,
+                "package.version.workspace".to_string(),
+                shared.clone(),
+            )),
+            _ => continue,
+        }
+        crates.push(name.to_string());
+    }
+
+    let lock_path = root_dir.join("Cargo.lock").to_string_lossy().to_string();
+    if Path::new(&lock_path).exists() {
+        let lock = parse(&lock_path, &read(&lock_path)?)?;
+        let packages = lock
+            .get("package")
+            .and_then(|packages| packages.as_array_of_tables());
+        for package in packages.into_iter().flat_map(|packages| packages.iter()) {
+            let name = package.get("name").and_then(|name| name.as_str());
+            let version = package.get("version").and_then(|version| version.as_str());
+            if let (Some(name), Some(version), None) = (name, version, package.get("source")) {
+                if crates.iter().any(|member| member == name) {
+                    versions.push((
+                        lock_path.clone(),
+                        format!("package.\"{name}\".version"),
+                        version.to_string(),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(versions)
+}
+
+/// Finds the Cargo.toml of every member of the workspace, without the root manifest
+/// # Arguments
+/// * `manifest_path` - The path of the root Cargo.toml e.g. `./Cargo.toml`
+/// * `read` - Reads the current contents of a file
+pub(crate) fn workspace_members(
+    manifest_path: &str,
+    read: &dyn Fn(&str) -> Result<String, String>,
+) -> Result<Vec<String>, String> {
+    let root_dir = Path::new(manifest_path)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let root = parse(manifest_path, &read(manifest_path)?)?;
+    let workspace = root
+        .get("workspace")
+        .and_then(|workspace| workspace.as_table_like())
+        .ok_or_else(|| "missing [workspace] table".to_string())?;
+    member_manifests(root_dir, workspace)
+}
+
+/// The version the workspace shares before it is set, `[workspace.package]` first then the root `[package]`
+fn shared_version(root: &Document) -> Option<String> {
+    root.get("workspace")
+        .and_then(|workspace| workspace.get("package"))
+        .and_then(|package| package.get("version"))
+        .or_else(|| {
+            root.get("package")
+                .and_then(|package| package.get("version"))
+        })
+        .and_then(|version| version.as_str())
+        .map(|version| version.to_string())
+}
+
+fn parse(path: &str, file: &str) -> Result<Document, String> {
+    file.parse::<Document>()
+        .map_err(|err| format!("{path} is not valid TOML: {err}"))
+}
+
+/// Finds the Cargo.toml of every member listed in `workspace.members`, expanding globs
+/// and leaving out `workspace.exclude`
+fn member_manifests(root_dir: &Path, workspace: &dyn TableLike) -> Result<Vec<String>, String> {
+    let patterns = |key: &str| {
+        workspace
+            .get(key)
+            .and_then(|item| item.as_array())
+            .map(|array| {
+                array
+                    .iter()
+                    .filter_map(|pattern| pattern.as_str())
+                    .map(|pattern| pattern.to_string())
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default()
+    };
+    let excluded = patterns("exclude")
+        .iter()
+        .map(|path| root_dir.join(path))
+        .collect::<Vec<_>>();
+
+    let mut manifests = vec![];
+    for pattern in patterns("members") {
+        let directories = glob_directories(root_dir, &pattern)
+            .map_err(|err| format!("invalid workspace member '{pattern}': {err}"))?;
+        for relative in directories {
+            // the root package is already handled when the root lists itself as a member
+            if relative.is_empty() {
+                continue;
+            }
+            let directory = root_dir.join(relative);
+            let manifest = directory.join("Cargo.toml");
+            if manifest.is_file() && !excluded.contains(&directory) {
+                manifests.push(manifest.to_string_lossy().to_string());
+            }
+        }
+    }
+    manifests.sort();
+    manifests.dedup();
+    Ok(manifests)
+}
+
+/// Sets `[package].version` of a manifest if it holds the old shared version
+/// # Returns
+/// * The package name if its version is now the new version, either set directly
+///   or inherited with `version.workspace = true` from a shared version that was set
+fn set_package_version(
+    document: &mut Document,
+    version: &str,
+    old_version: &Option<String>,
+    shares_version: bool,
+) -> Option<String> {
+    let package = document.get_mut("package")?;
+    let name = package.get("name")?.as_str()?.to_string();
+    let item = package.get_mut("version")?;
+    let inherits = item
+        .get("workspace")
+        .and_then(|workspace| workspace.as_bool())
+        .unwrap_or(false);
+    // a member versioned on its own keeps its version
+    let follows = item.as_str().is_some() && item.as_str() == old_version.as_deref();
+    if (follows && set_string(item, version)) || (inherits && shares_version) {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// Updates the requirements on bumped crates in every dependency table of the manifest
+pub(crate) fn set_dependency_requirements(
+    document: &mut Document,
+    bumped: &HashSet<String>,
+    version: &str,
+) {
+    for table in DEPENDENCY_TABLES {
+        if let Some(dependencies) = document
+            .get_mut(table)
+            .and_then(|dependencies| dependencies.as_table_like_mut())
+        {
+            set_requirements(dependencies, bumped, version);
+        }
+    }
+    if let Some(targets) = document
+        .get_mut("target")
+        .and_then(|targets| targets.as_table_like_mut())
+    {
+        for (_, target) in targets.iter_mut() {
+            for table in DEPENDENCY_TABLES {
+                if let Some(dependencies) = target
+                    .get_mut(table)
+                    .and_then(|dependencies| dependencies.as_table_like_mut())
+                {
+                    set_requirements(dependencies, bumped, version);
+                }
+            }
+        }
+    }
+}
+
+/// Updates the version requirement of path dependencies on bumped crates, keeping the operator
+/// e.g. `foo = { path = "../foo", version = "=1.2.3" }` becomes `version = "=1.2.4"`
+fn set_requirements(dependencies: &mut dyn TableLike, bumped: &HashSet<String>, version: &str) {
+    for (key, dependency) in dependencies.iter_mut() {
+        let name = dependency
+            .get("package")
+            .and_then(|package| package.as_str())
+            .unwrap_or(key.get())
+            .to_string();
+        if !bumped.contains(&name) || dependency.get("path").is_none() {
+            continue;
+        }
+        let Some(requirement) = dependency.get_mut("version") else {
+            continue;
+        };
+        if let Some(current) = requirement.as_str() {
+            let operator_len = current.len()
+                - current
+                    .trim_start_matches(['=', '^', '~', '>', '<', ' '])
+                    .len();
+            let new_requirement = format!("{}{version}", &current[..operator_len]);
+            set_string(requirement, &new_requirement);
+        }
+    }
+}
+
+/// Replaces a string value keeping the comments and whitespace around it
+/// # Returns
+/// * true if the item held a string and was replaced
+fn set_string(item: &mut Item, new_value: &str) -> bool {
+    let Some(value) = item.as_value_mut().filter(|value| value.is_str()) else {
+        return false;
+    };
+    let decor = value.decor().clone();
+    *value = toml_edit::Value::from(new_value);
+    *value.decor_mut() = decor;
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_cargo_workspace_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, contents: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            r#"[workspace]
+members = ["crates/*"]
+exclude = ["crates/scratch"]
+
+[workspace.package]
+version = "1.2.3" # shared
+
+[workspace.dependencies]
+core = { path = "crates/core", version = "=1.2.3" }
+serde = "1.0"
+"#,
+        );
+        write(
+            "crates/core/Cargo.toml",
+            "[package]\nname = \"core\"\nversion.workspace = true\n",
+        );
+        write(
+            "crates/cli/Cargo.toml",
+            r#"[package]
+name = "cli"
+version = "1.2.3"
+
+[dependencies]
+core = { path = "../core", version = "1.2.3" }
+other-core = { package = "core", version = "1.0", registry = "internal" }
+
+[target.'cfg(unix)'.dev-dependencies]
+my-core = { package = "core", path = "../core", version = "^1.2.3" }
+"#,
+        );
+        write(
+            "crates/tool/Cargo.toml",
+            r#"[package]
+name = "tool"
+version = "0.4.0"
+
+[dependencies]
+core = { path = "../core", version = "1.2.3" }
+"#,
+        );
+        write(
+            "crates/scratch/Cargo.toml",
+            "[package]\nname = \"scratch\"\nversion = \"0.0.1\"\n",
+        );
+        write(
+            "Cargo.lock",
+            r#"version = 3
+
+[[package]]
+name = "cli"
+version = "1.2.3"
+
+[[package]]
+name = "core"
+version = "1.2.3"
+
+[[package]]
+name = "core"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "tool"
+version = "0.4.0"
+"#,
+        );
+
+        let root = dir.path().join("Cargo.toml").to_string_lossy().to_string();
+        let read = |path: &str| std::fs::read_to_string(path).map_err(|err| err.to_string());
+        let planned = plan_cargo_workspace_sync(&root, "1.3.0", &read).unwrap();
+        let planned = planned
+            .into_iter()
+            .map(|(path, contents)| {
+                let path = Path::new(&path).strip_prefix(dir.path()).unwrap();
+                (path.to_string_lossy().to_string(), contents)
+            })
+            .collect::<Vec<(String, String)>>();
+        let files = planned
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            files,
+            vec![
+                "Cargo.toml",
+                "crates/cli/Cargo.toml",
+                "crates/core/Cargo.toml",
+                "crates/tool/Cargo.toml",
+                "Cargo.lock"
+            ]
+        );
+
+        assert!(planned[0].1.contains("version = \"1.3.0\" # shared"));
+        assert!(planned[0].1.contains("version = \"=1.3.0\" }"));
+        assert!(planned[1].1.contains("name = \"cli\"\nversion = \"1.3.0\""));
+        assert!(planned[1]
+            .1
+            .contains("path = \"../core\", version = \"1.3.0\""));
+        assert!(planned[1].1.contains("version = \"1.0\", registry"));
+        assert!(planned[1].1.contains("version = \"^1.3.0\""));
+        assert_eq!(
+            planned[2].1,
+            "[package]\nname = \"core\"\nversion.workspace = true\n"
+        );
+        // a member versioned on its own keeps its version, only its requirements follow
+        assert!(planned[3]
+            .1
+            .contains("name = \"tool\"\nversion = \"0.4.0\""));
+        assert!(planned[3]
+            .1
+            .contains("path = \"../core\", version = \"1.3.0\""));
+        assert!(planned[4].1.contains("name = \"cli\"\nversion = \"1.3.0\""));
+        assert!(planned[4]
+            .1
+            .contains("name = \"core\"\nversion = \"1.3.0\""));
+        assert!(planned[4].1.contains("version = \"1.0.0\"\nsource"));
+        assert!(planned[4]
+            .1
+            .contains("name = \"tool\"\nversion = \"0.4.0\""));
+
+        let versions = read_cargo_workspace_versions(&root, &read).unwrap();
+        let keys = versions
+            .iter()
+            .map(|(path, key, version)| {
+                let path = Path::new(path).strip_prefix(dir.path()).unwrap();
+                format!("{} {key} {version}", path.to_string_lossy())
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(
+            keys,
+            vec![
+                "Cargo.toml workspace.package.version 1.2.3",
+                "crates/cli/Cargo.toml package.version 1.2.3",
+                "crates/core/Cargo.toml package.version.workspace 1.2.3",
+                "Cargo.lock package.\"cli\".version 1.2.3",
+                "Cargo.lock package.\"core\".version 1.2.3",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_workspace_version() {
+        assert_eq!(
+            read_workspace_version("[workspace]\n[workspace.package]\nversion = \"1.0.0\"\n")
+                .unwrap(),
+            "1.0.0"
+        );
+        assert!(read_workspace_version("[package]\nversion = \"1.0.0\"\n").is_err());
+    }
+}
@@ -0,0 +1,443 @@
+//! Cascading releases across the packages of a monorepo
+//!
+//! Packages depend on each other through the manifests in their version sync files: the dependency
+//! tables of a Cargo.toml and the dependency ranges of a package.json. When a package is released,
+//! every package that depends on it, directly or not, is released too with at least a patch bump,
+//! and its requirement on the released package follows the new version. A dependent that is a member
+//! of a release group brings the whole group along.
+use std::collections::HashSet;
+
+use indexmap::IndexMap;
+use toml_edit::Document;
+
+use crate::cargo_workspace::{self, set_dependency_requirements};
+use crate::config::{Config, PackageConfig};
+use crate::error::{GitScribeError, Result};
+use crate::node_workspace::{self, set_dependency_ranges};
+use crate::version::{Version, VersionDesignation};
+use crate::version_file_sync::{SupportedSyncFileFormat, VersionSyncFile};
+
+/// A package released as part of a cascade
+/// # Fields
+/// * `package` - The package name
+/// * `previous` - The version before the release
+/// * `version` - The new version
+/// * `updated_dependencies` - The released packages it depends on, empty for the packages released on purpose
+/// * `released_with_group` - The release group it is released with because another member depends on a released package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CascadeStep {
+    pub package: String,
+    pub previous: Version,
+    pub version: Version,
+    pub updated_dependencies: Vec<String>,
+    pub released_with_group: Option<String>,
+}
+
+impl std::fmt::Display for CascadeStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {} -> {}", self.package, self.previous, self.version)?;
+        if !self.updated_dependencies.is_empty() {
+            write!(
+                f,
+                " (dependency updated: {})",
+                self.updated_dependencies.join(", ")
+            )?;
+        } else if let Some(group) = &self.released_with_group {
+            write!(f, " (released with group {group})")?;
+        }
+        Ok(())
+    }
+}
+
+/// The kind of manifest a sync file is, which decides how dependencies on other packages are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ManifestKind {
+    Cargo,
+    Npm,
+}
+
+/// The name a manifest publishes and the names it depends on
+struct Manifest {
+    kind: ManifestKind,
+    name: Option<String>,
+    dependencies: HashSet<String>,
+}
+
+/// The manifest kind of a sync file, None for files that do not declare dependencies
+fn manifest_kind(version_file: &VersionSyncFile) -> Option<ManifestKind> {
+    match version_file.file_format {
+        SupportedSyncFileFormat::CargoToml | SupportedSyncFileFormat::CargoWorkspace => {
+            Some(ManifestKind::Cargo)
+        }
+        SupportedSyncFileFormat::Json | SupportedSyncFileFormat::Node
+            if version_file.file_path.ends_with("package.json") =>
+        {
+            Some(ManifestKind::Npm)
+        }
+        _ => None,
+    }
+}
+
+/// Reads the package name and dependency names of a manifest
+fn read_manifest(kind: ManifestKind, file: &str) -> std::result::Result<Manifest, String> {
+    let (name, dependencies) = match kind {
+        ManifestKind::Cargo => {
+            let toml = toml::from_str::<toml::Value>(file).map_err(|err| err.to_string())?;
+            let name = toml
+                .get("package")
+                .and_then(|package| package.get("name"))
+                .and_then(|name| name.as_str())
+                .map(|name| name.to_string());
+            let targets = toml
+                .get("target")
+                .and_then(|targets| targets.as_table())
+                .into_iter()
+                .flat_map(|targets| targets.values());
+            let mut dependencies = HashSet::new();
+            for table in std::iter::once(&toml).chain(targets) {
+                for table in cargo_workspace::DEPENDENCY_TABLES
+                    .iter()
+                    .filter_map(|name| table.get(name).and_then(|table| table.as_table()))
+                {
+                    // a renamed dependency names the crate with `package`
+                    dependencies.extend(table.iter().map(|(key, dependency)| {
+                        dependency
+                            .get("package")
+                            .and_then(|package| package.as_str())
+                            .unwrap_or(key)
+                            .to_string()
+                    }));
+                }
+            }
+            (name, dependencies)
+        }
+        ManifestKind::Npm => {
+            let json =
+                serde_json::from_str::<serde_json::Value>(file).map_err(|err| err.to_string())?;
+            let name = json
+                .get("name")
+                .and_then(|name| name.as_str())
+                .map(|name| name.to_string());
+            let dependencies = node_workspace::DEPENDENCY_TABLES
+                .iter()
+                .filter_map(|table| json.get(table).and_then(|table| table.as_object()))
+                .flat_map(|table| table.keys().cloned())
+                .collect();
+            (name, dependencies)
+        }
+    };
+    Ok(Manifest {
+        kind,
+        name,
+        dependencies,
+    })
+}
+
+/// Reads every manifest among the sync files of a package
+fn package_manifests(package: &PackageConfig) -> Result<Vec<Manifest>> {
+    let mut manifests = vec![];
+    for version_file in &package.version_sync_files {
+        let Some(kind) = manifest_kind(version_file) else {
+            continue;
+        };
+        let file = std::fs::read_to_string(&version_file.file_path)
+            .map_err(|err| GitScribeError::sync_file(&version_file.file_path, err))?;
+        manifests.push(
+            read_manifest(kind, &file)
+                .map_err(|reason| GitScribeError::sync_file(&version_file.file_path, reason))?,
+        );
+    }
+    Ok(manifests)
+}
+
+/// Builds the dependency graph of the packages from the manifests in their sync files
+/// # Arguments
+/// * `packages` - The packages of the monorepo
+/// # Returns
+/// * Each package name with the names of the packages it depends on
+/// # Errors
+/// * A sync file error if a manifest cannot be read or parsed
+pub fn dependency_graph(packages: &[PackageConfig]) -> Result<IndexMap<String, Vec<String>>> {
+    let mut manifests = IndexMap::new();
+    for package in packages {
+        manifests.insert(package.name.clone(), package_manifests(package)?);
+    }
+    let graph = manifests
+        .iter()
+        .map(|(name, own)| {
+            let dependencies = manifests
+                .iter()
+                .filter(|(other, _)| *other != name)
+                .filter(|(_, theirs)| {
+                    theirs.iter().any(|their| {
+                        own.iter().any(|manifest| {
+                            manifest.kind == their.kind
+                                && their
+                                    .name
+                                    .as_ref()
+                                    .is_some_and(|their| manifest.dependencies.contains(their))
+                        })
+                    })
+                })
+                .map(|(other, _)| other.clone())
+                .collect();
+            (name.clone(), dependencies)
+        })
+        .collect();
+    Ok(graph)
+}
+
+/// Plans the release of packages along with every package that depends on them
+/// # Arguments
+/// * `config` - The config struct
+/// * `released` - The names of the packages released on purpose
+/// * `version` - The new version of the released packages
+/// # Returns
+/// * The packages to release in topological order, dependencies before their dependents
+/// # Errors
+/// * A config error if a package or group member is not configured
+/// * A sync file error if a manifest cannot be read or parsed
+pub fn plan_cascade(
+    config: &Config,
+    released: &[String],
+    version: &Version,
+) -> Result<Vec<CascadeStep>> {
+    let graph = dependency_graph(&config.packages)?;
+    let mut steps: IndexMap<String, CascadeStep> = IndexMap::new();
+    for name in released {
+        let package = config.package(name)?;
+        steps.insert(
+            name.clone(),
+            CascadeStep {
+                package: name.clone(),
+                previous: Version::new(package.version.clone())?,
+                version: version.clone(),
+                updated_dependencies: vec![],
+                released_with_group: None,
+            },
+        );
+    }
+
+    let mut queue = released.to_vec();
+    while let Some(dependency) = queue.pop() {
+        for (dependent, dependencies) in &graph {
+            if !dependencies.contains(&dependency) {
+                continue;
+            }
+            if let Some(step) = steps.get_mut(dependent) {
+                let cascaded = !released.contains(dependent);
+                if cascaded && !step.updated_dependencies.contains(&dependency) {
+                    step.updated_dependencies.push(dependency.clone());
+                }
+                continue;
+            }
+            // a dependent in a release group is released with the whole group
+            let (group_name, members, version) = match config.group_for(dependent) {
+                Some(group) => (
+                    Some(group.name.clone()),
+                    group.members.clone(),
+                    config
+                        .group_version(group)?
+                        .bump(&VersionDesignation::Patch),
+                ),
+                None => (
+                    None,
+                    vec![dependent.clone()],
+                    Version::new(config.package(dependent)?.version.clone())?
+                        .bump(&VersionDesignation::Patch),
+                ),
+            };
+            for member in members {
+                if steps.contains_key(&member) {
+                    continue;
+                }
+                // the other members only come along with the group, without depending on the package
+                let updated_dependencies = match graph
+                    .get(&member)
+                    .is_some_and(|of| of.contains(&dependency))
+                {
+                    true => vec![dependency.clone()],
+                    false => vec![],
+                };
+                steps.insert(
+                    member.clone(),
+                    CascadeStep {
+                        package: member.clone(),
+                        previous: Version::new(config.package(&member)?.version.clone())?,
+                        version: version.clone(),
+                        updated_dependencies,
+                        released_with_group: group_name.clone(),
+                    },
+                );
+                queue.push(member);
+            }
+        }
+    }
+
+    // dependencies first, a dependency cycle falls back to the order the packages were planned in
+    let mut ordered: Vec<CascadeStep> = vec![];
+    while !steps.is_empty() {
+        let placed = |name: &String| ordered.iter().any(|step| &step.package == name);
+        let next = steps
+            .keys()
+            .position(|name| {
+                graph[name]
+                    .iter()
+                    .all(|dependency| !steps.contains_key(dependency) || placed(dependency))
+            })
+            .unwrap_or(0);
+        let (_, step) = steps.shift_remove_index(next).expect("steps is not empty");
+        ordered.push(step);
+    }
+    Ok(ordered)
+}
+
+/// Moves the requirements of the released packages on each other to the new versions
+///
+/// Only Cargo path dependencies and package.json ranges that pin a version are updated
+/// # Arguments
+/// * `planned` - The planned file contents, updated in place
+/// * `config` - The config struct
+/// * `steps` - The planned cascade
+/// # Errors
+/// * A sync file error if a manifest cannot be read or updated
+pub(crate) fn plan_requirement_updates(
+    planned: &mut IndexMap<String, String>,
+    config: &Config,
+    steps: &[CascadeStep],
+) -> Result<()> {
+    for step in steps {
+        // the names every other released package publishes, with its new version
+        let mut updated = vec![];
+        for released in steps.iter().filter(|other| other.package != step.package) {
+            for manifest in package_manifests(config.package(&released.package)?)? {
+                if let Some(name) = manifest.name {
+                    updated.push((manifest.kind, name, released.version.to_string()));
+                }
+            }
+        }
+        if updated.is_empty() {
+            continue;
+        }
+
+        for version_file in &config.package(&step.package)?.version_sync_files {
+            let Some(kind) = manifest_kind(version_file) else {
+                continue;
+            };
+            let file_path = &version_file.file_path;
+            let current = match planned.get(file_path) {
+                Some(contents) => contents.clone(),
+                None => std::fs::read_to_string(file_path)
+                    .map_err(|err| GitScribeError::sync_file(file_path, err))?,
+            };
+            let mut contents = current.clone();
+            for (_, name, version) in updated.iter().filter(|(of, _, _)| *of == kind) {
+                let bumped = HashSet::from([name.clone()]);
+                contents = match kind {
+                    ManifestKind::Cargo => {
+                        let mut document = contents
+                            .parse::<Document>()
+                            .map_err(|err| GitScribeError::sync_file(file_path, err))?;
+                        set_dependency_requirements(&mut document, &bumped, version);
+                        document.to_string()
+                    }
+                    ManifestKind::Npm => set_dependency_ranges(&contents, &[], &bumped, version)
+                        .map_err(|reason| GitScribeError::sync_file(file_path, reason))?,
+                };
+            }
+            if contents != current {
+                planned.insert(file_path.clone(), contents);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_cascade() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |file: &str| dir.path().join(file).to_string_lossy().to_string();
+        let write = |file: &str, contents: &str| {
+            let file = dir.path().join(file);
+            std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+            std::fs::write(file, contents).unwrap();
+        };
+        write(
+            "protocol/Cargo.toml",
+            "[package]\nname = \"protocol\"\nversion = \"1.2.0\"\n",
+        );
+        write(
+            "sdk/Cargo.toml",
+            "[package]\nname = \"sdk\"\nversion = \"1.2.0\"\n\n[dependencies]\nproto = { package = \"protocol\", path = \"../protocol\", version = \"^1.2.0\" }\n",
+        );
+        write(
+            "cli/Cargo.toml",
+            "[package]\nname = \"cli\"\nversion = \"0.4.1\"\n\n[dev-dependencies]\nsdk = { path = \"../sdk\", version = \"1.2.0\" }\n",
+        );
+        write("web/package.json", r#"{"name": "web", "version": "3.0.0"}"#);
+        let mut config: Config = serde_json::from_str(&format!(
+            r#"{{
+                "version": "1.0.0", "commands_that_release": [], "branch_for_release": false,
+                "changelog_output_selections": [], "project_repo": null, "version_sync_files": null,
+                "packages": [
+                    {{"name": "cli", "version": "0.4.1", "version_sync_files": [{{"file_format": "CargoToml", "file_path": "{}", "version_key": "version"}}]}},
+                    {{"name": "sdk", "version": "1.2.0", "version_sync_files": [{{"file_format": "CargoToml", "file_path": "{}", "version_key": "version"}}]}},
+                    {{"name": "protocol", "version": "1.2.0", "version_sync_files": [{{"file_format": "CargoToml", "file_path": "{}", "version_key": "version"}}]}},
+                    {{"name": "web", "version": "3.0.0", "version_sync_files": [{{"file_format": "Json", "file_path": "{}", "version_key": "version"}}]}}
+                ]
+            }}"#,
+            path("cli/Cargo.toml"),
+            path("sdk/Cargo.toml"),
+            path("protocol/Cargo.toml"),
+            path("web/package.json"),
+        ))
+        .unwrap();
+
+        let graph = dependency_graph(&config.packages).unwrap();
+        assert_eq!(graph["cli"], vec!["sdk"]);
+        assert_eq!(graph["sdk"], vec!["protocol"]);
+        assert!(graph["web"].is_empty());
+
+        let version = Version::new("1.3.0".to_string()).unwrap();
+        let steps = plan_cascade(&config, &["protocol".to_string()], &version).unwrap();
+        assert_eq!(
+            steps
+                .iter()
+                .map(|step| step.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "protocol 1.2.0 -> 1.3.0",
+                "sdk 1.2.0 -> 1.2.1 (dependency updated: protocol)",
+                "cli 0.4.1 -> 0.4.2 (dependency updated: sdk)",
+            ]
+        );
+
+        let mut planned = IndexMap::new();
+        plan_requirement_updates(&mut planned, &config, &steps).unwrap();
+        assert!(planned[&path("sdk/Cargo.toml")].contains("version = \"^1.3.0\""));
+        assert!(planned[&path("cli/Cargo.toml")].contains("version = \"1.2.1\""));
+        assert!(!planned.contains_key(&path("protocol/Cargo.toml")));
+
+        // web does not depend on sdk, it is only released along with its group
+        config.groups =
+            serde_json::from_str(r#"[{"name": "tools", "members": ["cli", "web"]}]"#).unwrap();
+        let steps = plan_cascade(&config, &["protocol".to_string()], &version).unwrap();
+        assert_eq!(
+            steps
+                .iter()
+                .map(|step| step.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "protocol 1.2.0 -> 1.3.0",
+                "sdk 1.2.0 -> 1.2.1 (dependency updated: protocol)",
+                "cli 0.4.1 -> 3.0.1 (dependency updated: sdk)",
+                "web 3.0.0 -> 3.0.1 (released with group tools)",
+            ]
+        );
+        assert!(steps[3].updated_dependencies.is_empty());
+    }
+}
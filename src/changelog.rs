@@ -1,10 +1,11 @@
 use indexmap::IndexMap;
 
-use crate::change::get_changes;
 use crate::change::Change;
-use crate::config::Config;
+use crate::change::{get_changes, get_group_changes, get_package_changes};
+use crate::config::{ChangelogOutputOption, Config, GroupConfig, PackageConfig};
+use crate::error::{GitScribeError, Result};
 use crate::version::Version;
-use askama::{Error, Template};
+use askama::Template;
 use chrono::Local;
 use serde::{Deserialize, Serialize};
 
@@ -44,6 +45,27 @@ impl TemplateOption {
     }
 }
 
+impl std::str::FromStr for TemplateOption {
+    type Err = GitScribeError;
+
+    /// Parses a template option by name, ignoring case. e.g. `markdown` or `VueQuasar`
+    fn from_str(value: &str) -> Result<Self> {
+        Self::values()
+            .into_iter()
+            .find(|option| option.to_string().eq_ignore_ascii_case(value.trim()))
+            .ok_or_else(|| {
+                GitScribeError::Parse(format!(
+                    "Unknown changelog template '{value}', expected one of: {}",
+                    Self::values()
+                        .iter()
+                        .map(|option| option.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ))
+            })
+    }
+}
+
 impl std::fmt::Display for TemplateOption {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
@@ -53,58 +75,165 @@ impl std::fmt::Display for TemplateOption {
     }
 }
 
-/// Generates the changelog
+/// Renders a single changelog to a string
 /// # Arguments
-/// * `template_option` - The template option
+/// * `template_option` - The template to render
 /// * `version` - The new version
+/// * `changes` - The changes grouped by version, as returned by `get_changes`
 /// # Returns
-/// * A result containing a success message or an error
-pub fn generate_and_insert_changelogs(
+/// * The rendered changelog, or a template error
+/// # Examples
+/// ```no_run
+/// use gitscribe::{changelog::{render_changelog, TemplateOption}, change::get_changes, config::load_config, version::Version};
+/// let config = load_config().unwrap();
+/// let version: Version = config.version.parse().unwrap();
+/// let changes = get_changes(&config, &version).unwrap();
+/// let markdown = render_changelog(&TemplateOption::Markdown, &version, &changes).unwrap();
+/// ```
+pub fn render_changelog(
+    template_option: &TemplateOption,
+    version: &Version,
+    changes: &IndexMap<String, ChangeTypeMap>,
+) -> Result<String> {
+    let date = Local::now().format("%Y-%m-%d").to_string();
+    let rendered_log = match template_option {
+        TemplateOption::Markdown => MarkdownChangelog {
+            version: version.to_string(),
+            date,
+            changes: changes.clone(),
+        }
+        .render()?,
+        TemplateOption::VueQuasar => VueQuasarChangelog {
+            version: version.to_string(),
+            date,
+            changes: changes.clone(),
+        }
+        .render()?,
+    };
+    Ok(rendered_log)
+}
+
+/// Renders every configured changelog without writing anything
+/// # Arguments
+/// * `version` - The new version
+/// * `config` - The config struct holding the changelog output selections
+/// # Returns
+/// * A result containing each output selection paired with its rendered changelog, or an error
+pub fn render_changelogs(
     version: &Version,
     config: &Config,
-) -> Result<Vec<String>, Error> {
-    // for each output selection, generate the changelog
-    let changes = get_changes(&config, &version);
-    let mut results = vec![];
-    config
-        .changelog_output_selections
-        .iter()
-        .for_each(|output_selection| match output_selection.template_option {
-            TemplateOption::Markdown => {
-                let changelog = MarkdownChangelog {
-                    version: version.to_string(),
-                    date: Local::now().format("%Y-%m-%d").to_string(),
-                    changes: changes.clone(),
-                };
-                let rendered_log = changelog.render().expect("Failed to render changelog");
-                insert_changelog(&output_selection.output_filepath, &rendered_log);
-                results.push(format!(
-                    " - Generated {} changelog at {}",
-                    output_selection.template_option, output_selection.output_filepath
-                ));
-            }
-            TemplateOption::VueQuasar => {
-                let changelog = VueQuasarChangelog {
-                    version: version.to_string(),
-                    date: Local::now().format("%Y-%m-%d").to_string(),
-                    changes: changes.clone(),
-                };
-                let rendered_log = changelog.render().expect("Failed to render changelog");
-                insert_changelog(&output_selection.output_filepath, &rendered_log);
-                results.push(format!(
-                    " - Generated {} changelog at {}",
-                    output_selection.template_option, output_selection.output_filepath
-                ));
-            }
-        });
-
-    Ok(results)
+) -> Result<Vec<(ChangelogOutputOption, String)>> {
+    let changes = get_changes(config, version)?;
+    render_outputs(&config.changelog_output_selections, version, &changes)
+}
+
+/// Renders every changelog of a monorepo package without writing anything
+/// # Arguments
+/// * `version` - The new version of the package
+/// * `config` - The config struct
+/// * `package` - The package holding the changelog output selections
+/// # Returns
+/// * A result containing each output selection paired with its rendered changelog, or an error
+pub fn render_package_changelogs(
+    version: &Version,
+    config: &Config,
+    package: &PackageConfig,
+) -> Result<Vec<(ChangelogOutputOption, String)>> {
+    render_cascaded_changelogs(version, config, package, &[])
 }
 
-/// Inserts the changelog into the changelog file
+/// Renders every changelog of a monorepo package released along with its dependencies
+///
+/// A `dependency updated` entry is listed under the new version for each released dependency
 /// # Arguments
+/// * `version` - The new version of the package
 /// * `config` - The config struct
-/// * `changelog` - The changelog
-pub fn insert_changelog(output_filepath: &str, changelog: &str) {
-    std::fs::write(output_filepath, changelog).expect("Failed to write to file");
+/// * `package` - The package holding the changelog output selections
+/// * `updated_dependencies` - The released dependencies of the package with their new versions
+/// # Returns
+/// * A result containing each output selection paired with its rendered changelog, or an error
+pub fn render_cascaded_changelogs(
+    version: &Version,
+    config: &Config,
+    package: &PackageConfig,
+    updated_dependencies: &[(String, Version)],
+) -> Result<Vec<(ChangelogOutputOption, String)>> {
+    let mut changes = get_package_changes(config, package, version)?;
+    if let Some(version_changes) = changes.get_mut(&version.to_string()) {
+        for (dependency, dependency_version) in updated_dependencies {
+            version_changes
+                .fixes
+                .push(Change::dependency_updated(dependency, dependency_version));
+        }
+    }
+    render_outputs(&package.changelog_output_selections, version, &changes)
+}
+
+/// Renders the combined changelogs of a release group without writing anything
+/// # Arguments
+/// * `version` - The new version of the group
+/// * `config` - The config struct
+/// * `group` - The group holding the changelog output selections
+/// * `members` - The member packages of the group
+/// # Returns
+/// * A result containing each output selection paired with its rendered changelog, or an error
+pub fn render_group_changelogs(
+    version: &Version,
+    config: &Config,
+    group: &GroupConfig,
+    members: &[PackageConfig],
+) -> Result<Vec<(ChangelogOutputOption, String)>> {
+    let changes = get_group_changes(config, members, version)?;
+    render_outputs(&group.changelog_output_selections, version, &changes)
+}
+
+/// Renders the changes for each output selection
+fn render_outputs(
+    output_selections: &[ChangelogOutputOption],
+    version: &Version,
+    changes: &IndexMap<String, ChangeTypeMap>,
+) -> Result<Vec<(ChangelogOutputOption, String)>> {
+    output_selections
+        .iter()
+        .map(|output_selection| {
+            let rendered_log =
+                render_changelog(&output_selection.template_option, version, changes)?;
+            Ok((output_selection.clone(), rendered_log))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::change::ChangeType;
+    use chrono::NaiveDateTime;
+
+    #[test]
+    fn test_render_changelog() {
+        let mut changes = IndexMap::new();
+        changes.insert(
+            "1.1.0".to_string(),
+            ChangeTypeMap {
+                features: vec![Change {
+                    message: "feat: add library api".to_string(),
+                    commit_id: "abc123".to_string(),
+                    link: None,
+                    author: "John Doe".to_string(),
+                    change_type: ChangeType::Feature,
+                    date: NaiveDateTime::parse_from_str("2024-02-09 00:40:40", "%Y-%m-%d %H:%M:%S")
+                        .unwrap(),
+                    scope: None,
+                    files: vec![],
+                }],
+                fixes: vec![],
+            },
+        );
+        let version = Version::new("1.1.0".to_string()).unwrap();
+        let rendered = render_changelog(&TemplateOption::Markdown, &version, &changes).unwrap();
+
+        assert!(rendered.contains("release: 1.1.0"));
+        assert!(rendered.contains("### Added in 1.1.0"));
+        assert!(rendered.contains("- feat: add library api By: John Doe"));
+    }
 }
@@ -1,71 +1,848 @@
+use crate::change::VERSION_COMMIT_MESSAGE;
 use crate::changelog::TemplateOption;
+use crate::error::{GitScribeError, Result};
+use crate::hooks::ReleaseHooks;
+use crate::version::{Version, VersionDesignation};
 use crate::version_file_sync::VersionSyncFile;
 use crate::EXPECTED_CONFIG_FILE_NAME;
-use colored::Colorize;
 use serde::{Deserialize, Serialize};
 
+/// The version of the config format this gitscribe reads and writes
+///
+/// Configs without `config_version` predate it and are version 0, `gitscribe config migrate` upgrades them
+pub const CONFIG_VERSION: u32 = 1;
+
+/// The JSON Schema of the config file, set as `$schema` so editors can autocomplete it
+pub const CONFIG_SCHEMA_URL: &str =
+    "https://raw.githubusercontent.com/Kade-Powell/gitscribe/main/gitscribe.schema.json";
+
+/// The top level fields of `Config`, unversioned configs were read leniently and may hold other keys
+const CONFIG_FIELDS: [&str; 24] = [
+    "$schema",
+    "config_version",
+    "version",
+    "commands_that_release",
+    "branch_for_release",
+    "changelog_output_selections",
+    "project_repo",
+    "version_sync_files",
+    "release_branch_pattern",
+    "push",
+    "push_remote",
+    "commit_message",
+    "sign_commits",
+    "signing_key",
+    "commit_author",
+    "commit_committer",
+    "commit_trailers",
+    "tag_prefix",
+    "branch_policy",
+    "hooks",
+    "packages",
+    "groups",
+    "include_paths",
+    "exclude_paths",
+];
+
+/// The fields of the entries of the lists unversioned configs already had, with the list they are in
+const LEGACY_ENTRY_FIELDS: [(&str, &[&str]); 2] = [
+    (
+        "changelog_output_selections",
+        &["template_option", "output_filepath"],
+    ),
+    (
+        "version_sync_files",
+        &[
+            "file_format",
+            "file_path",
+            "version_key",
+            "additional_version_keys",
+        ],
+    ),
+];
+
 /// Struct Representing the Config file
 ///
+/// Every field but `version` has a default, and unknown fields are rejected so typos do not go unnoticed
 /// # Fields
 ///
+/// * `schema` - the OPTIONAL `$schema` of the file, see `CONFIG_SCHEMA_URL`
+/// * `config_version` - the version of the config format, see `CONFIG_VERSION`
 /// * `version` - the application version
+/// * `commands_that_release` - the commands that create a release branch when `branch_for_release` is on
+/// * `branch_for_release` - create a release branch for the commands in `commands_that_release`
 /// * `changelog_output_selections` - the list of changelog output selections
 /// * `project_repo` - the OPTIONAL project repository - used to make links to commits
 /// * `version_sync_files` - the OPTIONAL list of files to sync the version number to. eg. Cargo.toml, package.json, pyproject.toml
+/// * `packages` - the independently released packages of a monorepo, released with `--package`
+/// * `release_branch_pattern` - the OPTIONAL name of the release branches, `{major}`, `{minor}`, `{patch}` and `{version}`
+///   are replaced with the release version e.g. `release/{major}.{minor}.x`
+/// * `branch_policy` - the OPTIONAL branches each kind of release can be made from
+/// * `hooks` - the OPTIONAL commands run at each stage of a release, see `ReleaseHooks`
+/// * `push` - push the release commit, its tags and release branch after releasing, like `--push`
+/// * `push_remote` - the OPTIONAL remote releases are pushed to, defaults to `origin`
+/// * `commit_message` - the OPTIONAL release commit message template, see `release_commit_message`
+/// * `sign_commits` - sign the release commit, with `signing_key` or git's `user.signingkey`
+/// * `signing_key` - the OPTIONAL key the release commit is signed with
+/// * `commit_author` - the OPTIONAL author of the release commit e.g. `Release Bot <bot@example.com>`
+/// * `commit_committer` - the OPTIONAL committer of the release commit, in the same form as `commit_author`
+/// * `commit_trailers` - trailers added to the release commit message e.g. `Release-By: ci`
+/// * `tag_prefix` - the prefix of the tags of hotfix releases made on release branches, defaults to `v` e.g. `v1.4.3`
+/// * `groups` - the packages of a monorepo that are always released together with the same version
+/// * `include_paths` - globs of the files a commit must touch to be listed in the changelogs e.g. `src/**`
+/// * `exclude_paths` - globs of files that do not count towards a commit being listed e.g. `docs/**`
 ///
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    #[serde(default, skip_serializing_if = "is_unversioned")]
+    pub config_version: u32,
     pub version: String,
+    #[serde(default)]
     pub commands_that_release: Vec<String>,
+    #[serde(default)]
     pub branch_for_release: bool,
+    #[serde(default = "default_changelog_output_selections")]
     pub changelog_output_selections: Vec<ChangelogOutputOption>,
+    #[serde(default)]
     pub project_repo: Option<String>,
+    #[serde(default)]
     pub version_sync_files: Option<Vec<VersionSyncFile>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub release_branch_pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub push: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub push_remote: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_message: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sign_commits: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_committer: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commit_trailers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_prefix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_policy: Option<BranchPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<ReleaseHooks>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<PackageConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<GroupConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_paths: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_paths: Vec<String>,
+}
+
+/// Struct Representing a package of a monorepo that is versioned and released on its own
+///
+/// A commit belongs to the package if it touches a file under one of its `paths`,
+/// or if its conventional commit scope is the package name or one of its `scopes` e.g. `feat(api): ...`
+/// # Fields
+///
+/// * `name` - the package name, used with `--package`
+/// * `version` - the package version
+/// * `paths` - the directories of the package relative to the repo root e.g. `services/api`
+/// * `scopes` - other commit scopes that belong to the package
+/// * `changelog_output_selections` - the changelogs of the package
+/// * `version_sync_files` - the files to sync the package version to
+/// * `tag_prefix` - the prefix of the release tags, defaults to `<name>@` e.g. `api@1.2.0`
+///
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PackageConfig {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub paths: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+    #[serde(default)]
+    pub changelog_output_selections: Vec<ChangelogOutputOption>,
+    #[serde(default)]
+    pub version_sync_files: Vec<VersionSyncFile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_prefix: Option<String>,
+}
+
+impl PackageConfig {
+    /// The prefix of the package's release tags and release commits
+    pub fn tag_prefix(&self) -> String {
+        self.tag_prefix
+            .clone()
+            .unwrap_or_else(|| format!("{}@", self.name))
+    }
+
+    /// Returns true if the commit touches the package's files or is scoped to the package
+    /// # Arguments
+    /// * `files` - The files changed by the commit, relative to the repo root
+    /// * `scope` - The conventional commit scope, if any
+    pub fn owns(&self, files: &[String], scope: Option<&str>) -> bool {
+        let scoped = scope.is_some_and(|scope| {
+            scope == self.name
+                || self
+                    .scopes
+                    .iter()
+                    .any(|package_scope| package_scope == scope)
+        });
+        scoped
+            || self.paths.iter().any(|path| {
+                let path = path.trim_start_matches("./").trim_end_matches('/');
+                files.iter().any(|file| {
+                    path.is_empty()
+                        || path == "."
+                        || file == path
+                        || file
+                            .strip_prefix(path)
+                            .is_some_and(|rest| rest.starts_with('/'))
+                })
+            })
+    }
+}
+
+/// Struct Representing a group of monorepo packages released in lockstep
+///
+/// Releasing the group, or any of its members with `--package`, bumps every member to the same version.
+/// Each member keeps its own changelogs, listing only its own changes.
+/// # Fields
+///
+/// * `name` - the group name, can be used with `--package`
+/// * `members` - the names of the packages in the group
+/// * `changelog_output_selections` - the combined changelogs listing the changes of every member
+///
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GroupConfig {
+    pub name: String,
+    pub members: Vec<String>,
+    #[serde(default)]
+    pub changelog_output_selections: Vec<ChangelogOutputOption>,
+}
+
+/// Struct Representing the branches releases can be made from
+///
+/// Each designation lists the glob patterns of the branches it is allowed on, an empty list allows any branch
+/// # Fields
+///
+/// * `major` - the branches major releases can be made from e.g. `["main"]`
+/// * `minor` - the branches minor releases can be made from
+/// * `patch` - the branches patch releases can be made from e.g. `["main", "release/*"]`
+/// * `require_up_to_date` - refuse to release when the branch is behind its upstream, defaults to true
+/// * `require_pushed` - refuse to release when the branch has commits its upstream does not, defaults to true
+///
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BranchPolicy {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub major: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub minor: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patch: Vec<String>,
+    #[serde(default = "enabled")]
+    pub require_up_to_date: bool,
+    #[serde(default = "enabled")]
+    pub require_pushed: bool,
+}
+
+impl BranchPolicy {
+    /// The branch patterns a designation is allowed on
+    pub fn allowed_branches(&self, version_designation: &VersionDesignation) -> &[String] {
+        match version_designation {
+            VersionDesignation::Major => &self.major,
+            VersionDesignation::Minor => &self.minor,
+            VersionDesignation::Patch => &self.patch,
+        }
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// A config without `changelog_output_selections` writes the Markdown changelog, as a new config does
+fn default_changelog_output_selections() -> Vec<ChangelogOutputOption> {
+    vec![ChangelogOutputOption {
+        template_option: TemplateOption::Markdown,
+        output_filepath: "CHANGELOG.md".to_string(),
+    }]
+}
+
+/// Configs written before `config_version` existed keep leaving it out until they are migrated
+fn is_unversioned(config_version: &u32) -> bool {
+    *config_version == 0
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ChangelogOutputOption {
     pub template_option: TemplateOption,
     pub output_filepath: String,
 }
 
+impl std::str::FromStr for ChangelogOutputOption {
+    type Err = GitScribeError;
+
+    /// Parses a `TEMPLATE:PATH` spec. e.g. `markdown:CHANGELOG.md`
+    fn from_str(spec: &str) -> Result<Self> {
+        match spec.split_once(':') {
+            Some((template_option, output_filepath)) if !output_filepath.is_empty() => {
+                Ok(ChangelogOutputOption {
+                    template_option: template_option.parse()?,
+                    output_filepath: output_filepath.to_string(),
+                })
+            }
+            _ => Err(GitScribeError::Parse(format!(
+                "Invalid changelog output '{spec}', expected TEMPLATE:PATH"
+            ))),
+        }
+    }
+}
+
 impl Config {
     /// Creates a new instance of the Config struct with default values
     pub fn create_default() -> Self {
         Config {
+            schema: Some(CONFIG_SCHEMA_URL.to_string()),
+            config_version: CONFIG_VERSION,
             version: "0.0.1".to_string(),
             branch_for_release: false,
             commands_that_release: [].to_vec(),
             project_repo: None,
-            changelog_output_selections: vec![ChangelogOutputOption {
-                template_option: TemplateOption::Markdown,
-                output_filepath: "CHANGELOG.md".to_string(),
-            }],
+            changelog_output_selections: default_changelog_output_selections(),
             version_sync_files: None,
+            release_branch_pattern: None,
+            push: false,
+            push_remote: None,
+            commit_message: None,
+            sign_commits: false,
+            signing_key: None,
+            commit_author: None,
+            commit_committer: None,
+            commit_trailers: vec![],
+            tag_prefix: None,
+            branch_policy: None,
+            hooks: None,
+            packages: vec![],
+            groups: vec![],
+            include_paths: vec![],
+            exclude_paths: vec![],
+        }
+    }
+
+    /// Fills in the release commit message template, or the legacy message without one
+    ///
+    /// `{version}` must be on the first line of the template, as it is how past releases are found in the log
+    /// # Arguments
+    /// * `version` - What was released, the version or for packages their tags e.g. `api@1.2.0, web@0.3.1`
+    /// * `previous` - The same for the versions before the release
+    /// * `package` - The names of the released packages, empty for the root version
+    /// # Errors
+    /// * A config error if the first line of the template has no `{version}`
+    pub fn release_commit_message(
+        &self,
+        version: &str,
+        previous: &str,
+        package: &str,
+    ) -> Result<String> {
+        let Some(template) = &self.commit_message else {
+            return Ok(format!("{VERSION_COMMIT_MESSAGE}{version}"));
+        };
+        if !template
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .contains("{version}")
+        {
+            return Err(GitScribeError::Config(format!(
+                "commit_message '{template}' must have {{version}} on its first line"
+            )));
+        }
+        Ok(template
+            .replace("{version}", version)
+            .replace("{previous}", previous)
+            .replace("{package}", package))
+    }
+
+    /// The git arguments that make the release commit, with its signing, author and trailers
+    ///
+    /// The committer is set through the environment, see `release_commit_env`
+    /// # Arguments
+    /// * `message` - The release commit message, see `release_commit_message`
+    /// # Errors
+    /// * A config error if `commit_author` is not of the form `Name <email>`,
+    ///   or a trailer is not of the form `Key: value`
+    pub fn release_commit_args(&self, message: &str) -> Result<Vec<String>> {
+        let mut args = vec!["commit".to_string(), "-m".to_string(), message.to_string()];
+        if let Some(author) = &self.commit_author {
+            let (name, email) = parse_identity("commit_author", author)?;
+            args.push(format!("--author={name} <{email}>"));
+        }
+        if self.sign_commits {
+            match &self.signing_key {
+                Some(key) => args.push(format!("--gpg-sign={key}")),
+                None => args.push("--gpg-sign".to_string()),
+            }
+        }
+        for trailer in &self.commit_trailers {
+            match trailer.split_once(':') {
+                Some((key, value)) if !key.trim().is_empty() && !value.trim().is_empty() => {
+                    args.extend(["--trailer".to_string(), trailer.clone()]);
+                }
+                _ => {
+                    return Err(GitScribeError::Config(format!(
+                        "commit trailer '{trailer}' must be of the form 'Key: value'"
+                    )))
+                }
+            }
         }
+        Ok(args)
+    }
+
+    /// The environment of the release commit, setting its committer
+    ///
+    /// `GIT_COMMITTER_NAME` and `GIT_COMMITTER_EMAIL` only change the committer, where `user.name` and
+    /// `user.email` would make it the author too
+    /// # Errors
+    /// * A config error if `commit_committer` is not of the form `Name <email>`
+    pub fn release_commit_env(&self) -> Result<Vec<(String, String)>> {
+        let Some(committer) = &self.commit_committer else {
+            return Ok(vec![]);
+        };
+        let (name, email) = parse_identity("commit_committer", committer)?;
+        Ok(vec![
+            ("GIT_COMMITTER_NAME".to_string(), name.to_string()),
+            ("GIT_COMMITTER_EMAIL".to_string(), email.to_string()),
+        ])
+    }
+
+    /// The remote releases are pushed to
+    pub fn push_remote(&self) -> String {
+        self.push_remote
+            .clone()
+            .unwrap_or_else(|| "origin".to_string())
+    }
+
+    /// The prefix of the root version's release tags
+    pub fn tag_prefix(&self) -> String {
+        self.tag_prefix.clone().unwrap_or_else(|| "v".to_string())
+    }
+
+    /// The name pattern of the release branches
+    ///
+    /// Without `release_branch_pattern` the parts of the version not in `commands_that_release` are an `X`
+    /// e.g. `release/{major}.{minor}.X` when only major and minor releases are branched
+    pub fn release_branch_pattern(&self) -> String {
+        if let Some(pattern) = &self.release_branch_pattern {
+            return pattern.clone();
+        }
+        let part = |command: &str, placeholder: &'static str| match self
+            .commands_that_release
+            .iter()
+            .any(|released| released == command)
+        {
+            true => placeholder,
+            false => "X",
+        };
+        format!(
+            "release/{{major}}.{}.{}",
+            part("minor", "{minor}"),
+            part("patch", "{patch}")
+        )
+    }
+
+    /// Finds a package by name
+    /// # Errors
+    /// * A config error listing the configured packages if there is no package with the name
+    pub fn package(&self, name: &str) -> Result<&PackageConfig> {
+        self.packages
+            .iter()
+            .find(|package| package.name == name)
+            .ok_or_else(|| {
+                GitScribeError::Config(format!(
+                    "No package named '{name}' in {EXPECTED_CONFIG_FILE_NAME}, expected one of: {}",
+                    self.packages
+                        .iter()
+                        .map(|package| package.name.as_str())
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                ))
+            })
+    }
+
+    /// Finds the group released in lockstep with a package, or the group with the name
+    pub fn group_for(&self, name: &str) -> Option<&GroupConfig> {
+        self.groups
+            .iter()
+            .find(|group| group.name == name || group.members.iter().any(|member| member == name))
+    }
+
+    /// The current version of a group, the highest version of its members
+    /// # Errors
+    /// * A config error if a member is not a configured package or the group has no members
+    /// * A parse error if a member version is invalid
+    pub fn group_version(&self, group: &GroupConfig) -> Result<Version> {
+        let mut highest: Option<Version> = None;
+        for member in self.group_members(group)? {
+            let version = Version::new(member.version)?;
+            if highest.as_ref().is_none_or(|highest| version > *highest) {
+                highest = Some(version);
+            }
+        }
+        highest
+            .ok_or_else(|| GitScribeError::Config(format!("Group '{}' has no members", group.name)))
+    }
+
+    /// Finds every member package of a group
+    /// # Errors
+    /// * A config error if a member is not a configured package
+    pub fn group_members(&self, group: &GroupConfig) -> Result<Vec<PackageConfig>> {
+        group
+            .members
+            .iter()
+            .map(|member| {
+                self.package(member).cloned().map_err(|_| {
+                    GitScribeError::Config(format!(
+                        "Group '{}' lists '{member}', which is not a package in {EXPECTED_CONFIG_FILE_NAME}",
+                        group.name
+                    ))
+                })
+            })
+            .collect()
     }
 }
 
 /// Loads the config file
 /// # Returns
-/// * An Option containing the Config struct
-/// * If the file does not exist, an error message will be printed and None will be returned
-pub fn load_config() -> Option<Config> {
-    let config_file = std::fs::read_to_string(EXPECTED_CONFIG_FILE_NAME);
-    match config_file {
-        Ok(config_file) => match serde_json::from_str::<Config>(&config_file) {
-            Ok(config) => Some(config),
-            Err(msg) => {
-                println!("🤬Failed to parse config file: {}", msg.to_string().red());
-                None
+/// * A Result containing the Config struct
+/// * If the file does not exist or cannot be parsed, a config error is returned
+pub fn load_config() -> Result<Config> {
+    let config_file = std::fs::read_to_string(EXPECTED_CONFIG_FILE_NAME).map_err(|_| {
+        GitScribeError::Config(format!(
+            "Failed to read config file: {}. Please run `gitscribe init` to create a new config file.",
+            EXPECTED_CONFIG_FILE_NAME
+        ))
+    })?;
+    parse_config(&config_file)
+}
+
+/// Parses the contents of a config file
+/// # Errors
+/// * A config error if the contents are not a valid config, or the config was written for a newer gitscribe
+pub fn parse_config(contents: &str) -> Result<Config> {
+    serde_json::from_str::<Config>(contents).map_err(|msg| {
+        // a newer config likely fails on fields this gitscribe does not know, which is the real cause
+        let config_version = serde_json::from_str::<serde_json::Value>(contents)
+            .ok()
+            .and_then(|config| config.get("config_version")?.as_u64());
+        match config_version {
+            Some(config_version) if config_version > u64::from(CONFIG_VERSION) => {
+                newer_config_error(config_version)
+            }
+            _ => GitScribeError::Config(format!("Failed to parse config file: {msg}")),
+        }
+    })
+}
+
+/// Upgrades the contents of a config file to the current config version
+///
+/// The contents are upgraded as JSON before they are parsed, so keys unversioned configs could hold do not
+/// stop the migration: the keys gitscribe ignored before `config_version` existed are dropped.
+/// Fields older configs may leave out take their defaults, and `$schema` is set if the config has none
+/// # Returns
+/// * The upgraded config, the config version it was at and the keys that were dropped e.g. `version_sync_files[0].note`
+/// # Errors
+/// * A config error if the upgraded contents are not a valid config, or the config was written for a newer gitscribe
+pub fn migrate_config(contents: &str) -> Result<(Config, u32, Vec<String>)> {
+    let parse_error =
+        |msg: String| GitScribeError::Config(format!("Failed to parse config file: {msg}"));
+    let mut value = serde_json::from_str::<serde_json::Value>(contents)
+        .map_err(|err| parse_error(err.to_string()))?;
+    let config = value
+        .as_object_mut()
+        .ok_or_else(|| parse_error("the config is not a JSON object".to_string()))?;
+    let from = match config.get("config_version") {
+        Some(config_version) => config_version
+            .as_u64()
+            .ok_or_else(|| parse_error("config_version is not a number".to_string()))?,
+        None => 0,
+    };
+    if from > u64::from(CONFIG_VERSION) {
+        return Err(newer_config_error(from));
+    }
+
+    let mut dropped = vec![];
+    if from == 0 {
+        config.retain(|key, _| {
+            let known = CONFIG_FIELDS.contains(&key.as_str());
+            if !known {
+                dropped.push(key.clone());
+            }
+            known
+        });
+        for (list, fields) in LEGACY_ENTRY_FIELDS {
+            let entries = config
+                .get_mut(list)
+                .and_then(|entries| entries.as_array_mut());
+            for (index, entry) in entries.into_iter().flatten().enumerate() {
+                if let Some(entry) = entry.as_object_mut() {
+                    entry.retain(|key, _| {
+                        let known = fields.contains(&key.as_str());
+                        if !known {
+                            dropped.push(format!("{list}[{index}].{key}"));
+                        }
+                        known
+                    });
+                }
             }
-        },
-        Err(_) => {
-            println!(
-                "🤬Failed to read config file: {}. Please run `gitscribe init` to create a new config file.",
-                EXPECTED_CONFIG_FILE_NAME.red()
-            );
-            None
         }
     }
+    config.insert("config_version".to_string(), CONFIG_VERSION.into());
+    config
+        .entry("$schema")
+        .or_insert_with(|| CONFIG_SCHEMA_URL.into());
+
+    let config =
+        serde_json::from_value::<Config>(value).map_err(|err| parse_error(err.to_string()))?;
+    Ok((config, from as u32, dropped))
+}
+
+/// The error for a config written by a newer gitscribe
+fn newer_config_error(config_version: u64) -> GitScribeError {
+    GitScribeError::Config(format!(
+        "{EXPECTED_CONFIG_FILE_NAME} is config version {config_version}, \
+         this gitscribe reads up to version {CONFIG_VERSION}, please update gitscribe"
+    ))
+}
+
+/// Splits an identity of the form `Name <email>` into its name and email
+fn parse_identity<'a>(field: &str, identity: &'a str) -> Result<(&'a str, &'a str)> {
+    identity
+        .trim()
+        .strip_suffix('>')
+        .and_then(|identity| identity.split_once('<'))
+        .map(|(name, email)| (name.trim(), email.trim()))
+        .filter(|(name, email)| !name.is_empty() && !email.is_empty())
+        .ok_or_else(|| {
+            GitScribeError::Config(format!(
+                "{field} '{identity}' must be of the form 'Name <email>'"
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_owns_commits_by_path_or_scope() {
+        let package: PackageConfig = serde_json::from_str(
+            r#"{"name": "api", "version": "1.0.0", "paths": ["./services/api/"], "scopes": ["server"]}"#,
+        )
+        .unwrap();
+        assert_eq!(package.tag_prefix(), "api@");
+
+        let files = |files: &[&str]| {
+            files
+                .iter()
+                .map(|file| file.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert!(package.owns(&files(&["services/api/src/main.rs"]), None));
+        assert!(!package.owns(&files(&["services/api-gateway/main.rs"]), None));
+        assert!(!package.owns(&files(&["README.md"]), None));
+        assert!(package.owns(&[], Some("api")));
+        assert!(package.owns(&[], Some("server")));
+        assert!(!package.owns(&[], Some("web")));
+    }
+
+    #[test]
+    fn test_group_for_member_or_name() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "version": "1.0.0", "commands_that_release": [], "branch_for_release": false,
+                "changelog_output_selections": [], "project_repo": null, "version_sync_files": null,
+                "packages": [{"name": "sdk", "version": "2.1.0"}, {"name": "protocol", "version": "2.0.3"}],
+                "groups": [{"name": "client", "members": ["sdk", "protocol"]}]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.group_for("protocol").unwrap().name, "client");
+        assert_eq!(config.group_for("client").unwrap().name, "client");
+        assert!(config.group_for("api").is_none());
+        assert_eq!(config.group_members(&config.groups[0]).unwrap().len(), 2);
+
+        let mut config = config;
+        config.groups[0].members.push("api".to_string());
+        assert!(matches!(
+            config.group_members(&config.groups[0]),
+            Err(GitScribeError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_release_commit_args() {
+        let mut config = Config::create_default();
+        assert_eq!(
+            config.release_commit_args("release").unwrap(),
+            vec!["commit", "-m", "release"]
+        );
+
+        config.sign_commits = true;
+        config.commit_author = Some("Release Bot <bot@example.com>".to_string());
+        config.commit_committer = Some(" CI <ci@example.com> ".to_string());
+        config.commit_trailers = vec!["Release-By: ci".to_string()];
+        assert_eq!(
+            config.release_commit_args("release").unwrap(),
+            vec![
+                "commit",
+                "-m",
+                "release",
+                "--author=Release Bot <bot@example.com>",
+                "--gpg-sign",
+                "--trailer",
+                "Release-By: ci",
+            ]
+        );
+        assert_eq!(
+            config.release_commit_env().unwrap(),
+            vec![
+                ("GIT_COMMITTER_NAME".to_string(), "CI".to_string()),
+                (
+                    "GIT_COMMITTER_EMAIL".to_string(),
+                    "ci@example.com".to_string()
+                ),
+            ]
+        );
+
+        config.commit_author = Some("bot@example.com".to_string());
+        assert!(matches!(
+            config.release_commit_args("release"),
+            Err(GitScribeError::Config(_))
+        ));
+        config.commit_author = None;
+        config.commit_committer = Some("CI".to_string());
+        assert!(matches!(
+            config.release_commit_env(),
+            Err(GitScribeError::Config(_))
+        ));
+        config.commit_committer = None;
+        assert!(config.release_commit_env().unwrap().is_empty());
+        config.commit_trailers = vec!["Release-By".to_string()];
+        assert!(matches!(
+            config.release_commit_args("release"),
+            Err(GitScribeError::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_legacy_and_unknown_fields() {
+        // a config from before most fields existed
+        let config =
+            parse_config(r#"{"version": "1.0.0", "changelog_output_selections": []}"#).unwrap();
+        assert_eq!(config.config_version, 0);
+        assert!(!config.branch_for_release);
+        assert!(config.commands_that_release.is_empty());
+
+        let err = parse_config(r#"{"version": "1.0.0", "brnach_for_release": true}"#).unwrap_err();
+        assert!(err.to_string().contains("brnach_for_release"));
+        let err = parse_config(r#"{"config_version": 99, "version": "1.0.0", "new_field": 1}"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("please update gitscribe"));
+    }
+
+    #[test]
+    fn test_migrate_config() {
+        let (config, from, dropped) = migrate_config(r#"{"version": "1.0.0"}"#).unwrap();
+        assert_eq!(from, 0);
+        assert!(dropped.is_empty());
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        let migrated = serde_json::to_string_pretty(&config).unwrap();
+        assert!(migrated.starts_with(&format!(
+            "{{\n  \"$schema\": \"{CONFIG_SCHEMA_URL}\",\n  \"config_version\": {CONFIG_VERSION},"
+        )));
+
+        // a config without changelogs keeps writing the Markdown changelog, an empty list turns them off
+        assert_eq!(
+            serde_json::to_value(&config.changelog_output_selections).unwrap(),
+            serde_json::json!([{"template_option": "Markdown", "output_filepath": "CHANGELOG.md"}])
+        );
+        let (config, _, _) =
+            migrate_config(r#"{"version": "1.0.0", "changelog_output_selections": []}"#).unwrap();
+        assert!(config.changelog_output_selections.is_empty());
+
+        let (config, from, _) = migrate_config(&migrated).unwrap();
+        assert_eq!(from, CONFIG_VERSION);
+        assert_eq!(serde_json::to_string_pretty(&config).unwrap(), migrated);
+    }
+
+    #[test]
+    fn test_migrate_config_drops_legacy_keys() {
+        // keys an unversioned gitscribe ignored, which loading now rejects
+        let legacy = r#"{
+            "version": "1.0.0", "release_notes": true,
+            "version_sync_files": [
+                {"file_format": "CargoToml", "file_path": "Cargo.toml", "version_key": "version", "note": "x"}
+            ]
+        }"#;
+        assert!(parse_config(legacy).is_err());
+        let (config, from, dropped) = migrate_config(legacy).unwrap();
+        assert_eq!(from, 0);
+        assert_eq!(dropped, vec!["release_notes", "version_sync_files[0].note"]);
+        assert_eq!(config.version_sync_files.unwrap().len(), 1);
+
+        // a versioned config is held to its fields
+        let err =
+            migrate_config(r#"{"config_version": 1, "version": "1.0.0", "release_notes": true}"#)
+                .unwrap_err();
+        assert!(err.to_string().contains("release_notes"));
+        let err = migrate_config(r#"{"config_version": 99, "version": "1.0.0"}"#).unwrap_err();
+        assert!(err.to_string().contains("please update gitscribe"));
+    }
+
+    #[test]
+    fn test_schema_lists_every_field() {
+        let schema: serde_json::Value =
+            serde_json::from_str(include_str!("../gitscribe.schema.json")).unwrap();
+        let mut properties = schema["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        // every field set, so the config only parses if each name is a field
+        let config = r#"{
+            "$schema": "", "config_version": 1, "version": "1.0.0", "commands_that_release": [],
+            "branch_for_release": false, "changelog_output_selections": [], "project_repo": null,
+            "version_sync_files": null, "release_branch_pattern": "", "push": false, "push_remote": "",
+            "commit_message": "", "sign_commits": false, "signing_key": "", "commit_author": "",
+            "commit_committer": "", "commit_trailers": [], "tag_prefix": "", "branch_policy": {},
+            "hooks": {}, "packages": [], "groups": [], "include_paths": [], "exclude_paths": []
+        }"#;
+        parse_config(config).unwrap();
+        let mut fields = serde_json::from_str::<serde_json::Value>(config)
+            .unwrap()
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        properties.sort();
+        fields.sort();
+        assert_eq!(properties, fields);
+        let mut known = CONFIG_FIELDS.map(|field| field.to_string()).to_vec();
+        known.sort();
+        assert_eq!(known, fields);
+        assert_eq!(schema["$id"], CONFIG_SCHEMA_URL);
+    }
 }
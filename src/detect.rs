@@ -0,0 +1,244 @@
+use std::path::{Path, PathBuf};
+
+use crate::version_file_sync::{read_version_from_str, SupportedSyncFileFormat, VersionSyncFile};
+use crate::{cargo_workspace, node_workspace};
+
+/// How many directories deep the scan looks for manifests, enough for `packages/<name>/package.json`
+const MAX_SCAN_DEPTH: usize = 3;
+
+/// Directories that hold dependencies or build output rather than project manifests
+const SKIPPED_DIRECTORIES: [&str; 8] = [
+    "node_modules",
+    "target",
+    "vendor",
+    "dist",
+    "build",
+    "venv",
+    "__pypackages__",
+    "site-packages",
+];
+
+/// A version sync file found in the project along with the version it currently holds
+#[derive(Debug, Clone)]
+pub struct DetectedSyncFile {
+    pub version_sync_file: VersionSyncFile,
+    pub current_version: String,
+}
+impl std::fmt::Display for DetectedSyncFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({} {}) = {}",
+            self.version_sync_file.file_path,
+            self.version_sync_file.file_format,
+            self.version_sync_file.version_key,
+            self.current_version
+        )
+    }
+}
+
+/// The sync files a manifest can provide, a candidate is only kept if its key holds a version
+/// A Cargo workspace root or a package.json is only offered in the workspace-aware format,
+/// as that already covers the plain manifest
+fn candidates(file_name: &str) -> Vec<(SupportedSyncFileFormat, &'static str)> {
+    match file_name {
+        "Cargo.toml" => vec![
+            (SupportedSyncFileFormat::CargoWorkspace, "version"),
+            (SupportedSyncFileFormat::CargoToml, "version"),
+        ],
+        "package.json" => vec![
+            (SupportedSyncFileFormat::Node, "version"),
+            (SupportedSyncFileFormat::Json, "version"),
+        ],
+        "pyproject.toml" => vec![
+            (SupportedSyncFileFormat::PoetryToml, "version"),
+            (SupportedSyncFileFormat::PyprojectToml, "version"),
+        ],
+        "Chart.yaml" => vec![
+            (SupportedSyncFileFormat::Yaml, "version"),
+            (SupportedSyncFileFormat::Yaml, "appVersion"),
+        ],
+        "pubspec.yaml" => vec![(SupportedSyncFileFormat::Yaml, "version")],
+        _ => vec![],
+    }
+}
+
+/// Scans the project for manifests that hold a version
+/// Hidden directories and dependency or build directories are skipped, and so are the members
+/// of a detected Cargo or Node workspace, as the workspace entry already syncs them
+/// # Arguments
+/// * `root` - The root of the project
+/// # Returns
+/// * The detected sync files, with paths relative to the root e.g. `./Cargo.toml`
+pub fn detect_version_sync_files(root: &Path) -> Vec<DetectedSyncFile> {
+    let mut detected = vec![];
+    scan_directory(root, Path::new("."), 0, &mut detected, &mut vec![]);
+    detected
+}
+
+/// # Arguments
+/// * `members` - The manifests of the workspaces found so far, they are not offered on their own
+fn scan_directory(
+    root: &Path,
+    relative: &Path,
+    depth: usize,
+    detected: &mut Vec<DetectedSyncFile>,
+    members: &mut Vec<PathBuf>,
+) {
+    let Ok(entries) = std::fs::read_dir(root.join(relative)) else {
+        return;
+    };
+    let mut entries = entries
+        .filter_map(|entry| entry.ok())
+        .collect::<Vec<std::fs::DirEntry>>();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    let mut directories = vec![];
+    for entry in entries {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if !file_name.starts_with('.') && !SKIPPED_DIRECTORIES.contains(&file_name.as_str()) {
+                directories.push(relative.join(&file_name));
+            }
+            continue;
+        }
+        let candidates = candidates(&file_name);
+        if candidates.is_empty() || members.contains(&entry.path()) {
+            continue;
+        }
+        let Ok(contents) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        for (file_format, version_key) in candidates {
+            let version_sync_file = VersionSyncFile {
+                file_format,
+                file_path: relative.join(&file_name).to_string_lossy().to_string(),
+                version_key: version_key.to_string(),
+                additional_version_keys: vec![],
+            };
+            if let Ok(current_version) = read_version_from_str(&version_sync_file, &contents) {
+                let manifest_path = entry.path().to_string_lossy().to_string();
+                let read =
+                    |path: &str| std::fs::read_to_string(path).map_err(|err| err.to_string());
+                let workspace_members = match version_sync_file.file_format {
+                    SupportedSyncFileFormat::CargoWorkspace => {
+                        Some(cargo_workspace::workspace_members(&manifest_path, &read))
+                    }
+                    SupportedSyncFileFormat::Node => {
+                        Some(node_workspace::workspace_members(&manifest_path, &read))
+                    }
+                    _ => None,
+                };
+                detected.push(DetectedSyncFile {
+                    version_sync_file,
+                    current_version,
+                });
+                if let Some(workspace_members) = workspace_members {
+                    members.extend(
+                        workspace_members
+                            .unwrap_or_default()
+                            .iter()
+                            .map(PathBuf::from),
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    if depth < MAX_SCAN_DEPTH {
+        for directory in directories {
+            scan_directory(root, &directory, depth + 1, detected, members);
+        }
+    }
+}
+
+/// Returns the detected sync files whose current version differs from the given version
+/// # Arguments
+/// * `detected` - The detected sync files
+/// * `version` - The version the project is being initialized with
+pub fn mismatched_versions<'a>(
+    detected: &'a [DetectedSyncFile],
+    version: &str,
+) -> Vec<&'a DetectedSyncFile> {
+    detected
+        .iter()
+        .filter(|detected| detected.current_version != version)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_version_sync_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, contents: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"lib\", \"crates/*\"]\n\n[package]\nname = \"app\"\nversion = \"1.0.0\"\n",
+        );
+        // members with their own version are synced by the workspace entry, not offered again
+        write(
+            "crates/tool/Cargo.toml",
+            "[package]\nname = \"tool\"\nversion = \"0.4.2\"\n",
+        );
+        write(
+            "lib/Cargo.toml",
+            "[package]\nname = \"lib\"\nversion.workspace = true\n",
+        );
+        write(
+            "web/package.json",
+            r#"{"name": "web", "version": "1.0.0", "workspaces": ["packages/*"]}"#,
+        );
+        write(
+            "web/packages/ui/package.json",
+            r#"{"name": "ui", "version": "1.0.0"}"#,
+        );
+        write(
+            "py/pyproject.toml",
+            "[project]\nname = \"py\"\nversion = \"0.9.0\"\n",
+        );
+        write(
+            "chart/Chart.yaml",
+            "version: 1.0.0\nappVersion: \"1.0.0\"\n",
+        );
+        write(
+            "web/node_modules/dep/package.json",
+            r#"{"version": "9.9.9"}"#,
+        );
+        write(".hidden/package.json", r#"{"version": "9.9.9"}"#);
+
+        let detected = detect_version_sync_files(dir.path());
+        let found = detected
+            .iter()
+            .map(|detected| detected.to_string())
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            found,
+            vec![
+                "./Cargo.toml (CargoWorkspace version) = 1.0.0",
+                "./chart/Chart.yaml (Yaml version) = 1.0.0",
+                "./chart/Chart.yaml (Yaml appVersion) = 1.0.0",
+                "./py/pyproject.toml (PyprojectToml version) = 0.9.0",
+                "./web/package.json (Node version) = 1.0.0",
+            ]
+        );
+
+        let mismatched = mismatched_versions(&detected, "1.0.0");
+        assert_eq!(mismatched.len(), 1);
+        assert_eq!(
+            mismatched[0].version_sync_file.file_path,
+            "./py/pyproject.toml"
+        );
+    }
+}
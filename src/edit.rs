@@ -0,0 +1,683 @@
+//! Format-preserving edits of JSON and YAML files
+//!
+//! Both editors locate the byte range of a single scalar value and splice the new value in,
+//! so indentation, key order, comments, anchors and trailing newlines are left untouched.
+use std::ops::Range;
+
+/// A single step of a key path, either a key of a mapping or an index of an array
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+impl std::fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PathSegment::Key(key) => write!(f, "{key}"),
+            PathSegment::Index(index) => write!(f, "[{index}]"),
+        }
+    }
+}
+
+/// Parses a dotted key path with optional array indexes e.g. `spec.containers[0].image`
+///
+/// A key holding dots is quoted or has its dots escaped, e.g. `metadata.labels."app.kubernetes.io/version"`
+/// or `metadata.labels.app\.kubernetes\.io/version`. In quotes `\"` and `\\` are a quote and a backslash
+/// # Arguments
+/// * `path` - The key path
+/// # Returns
+/// * The segments of the path, or the reason the path is invalid
+pub(crate) fn parse_key_path(path: &str) -> Result<Vec<PathSegment>, String> {
+    let invalid = || format!("invalid key path '{path}'");
+    let mut segments = vec![];
+    let mut chars = path.chars().peekable();
+    loop {
+        let mut key = String::new();
+        let quoted = chars.peek() == Some(&'"');
+        if quoted {
+            chars.next();
+            loop {
+                match chars.next().ok_or_else(invalid)? {
+                    '"' => break,
+                    '\\' => key.push(chars.next().ok_or_else(invalid)?),
+                    c => key.push(c),
+                }
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c == '.' || c == '[' {
+                    break;
+                }
+                chars.next();
+                match c {
+                    '\\' => key.push(chars.next().ok_or_else(invalid)?),
+                    c => key.push(c),
+                }
+            }
+        }
+        let mut indexes = vec![];
+        while chars.peek() == Some(&'[') {
+            chars.next();
+            let mut index = String::new();
+            loop {
+                match chars.next().ok_or_else(invalid)? {
+                    ']' => break,
+                    c => index.push(c),
+                }
+            }
+            indexes.push(PathSegment::Index(index.parse().map_err(|_| invalid())?));
+        }
+        if quoted || !key.is_empty() {
+            segments.push(PathSegment::Key(key));
+        } else if indexes.is_empty() {
+            return Err(invalid());
+        }
+        segments.extend(indexes);
+        match chars.next() {
+            None => return Ok(segments),
+            Some('.') => continue,
+            Some(_) => return Err(invalid()),
+        }
+    }
+}
+
+/// Joins path segments back into the dotted form, used in error messages
+/// Keys holding dots or brackets are quoted
+pub(crate) fn display_key_path(path: &[PathSegment]) -> String {
+    path.iter()
+        .enumerate()
+        .map(|(position, segment)| {
+            let segment = match segment {
+                PathSegment::Key(key) if key.contains(['.', '[', '"', '\\']) => {
+                    format!("\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\""))
+                }
+                segment => segment.to_string(),
+            };
+            match path[position] {
+                PathSegment::Key(_) if position > 0 => format!(".{segment}"),
+                _ => segment,
+            }
+        })
+        .collect()
+}
+
+/// A minimal JSON scanner, the document is validated with serde_json before it is scanned
+struct JsonScanner<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> JsonScanner<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    /// Skips a string starting at the opening quote and returns its range including the quotes
+    fn skip_string(&mut self) -> Result<Range<usize>, String> {
+        let start = self.pos;
+        self.pos += 1;
+        while let Some(byte) = self.peek() {
+            self.pos += 1;
+            match byte {
+                b'\\' => self.pos += 1,
+                b'"' => return Ok(start..self.pos),
+                _ => {}
+            }
+        }
+        Err("unterminated JSON string".to_string())
+    }
+
+    /// Skips any value, nested objects and arrays included
+    fn skip_value(&mut self) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'"') => self.skip_string().map(|_| ()),
+            Some(open @ (b'{' | b'[')) => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(byte) if byte == close => {
+                            self.pos += 1;
+                            return Ok(());
+                        }
+                        Some(b',' | b':') => self.pos += 1,
+                        Some(_) => self.skip_value()?,
+                        None => return Err("unterminated JSON value".to_string()),
+                    }
+                }
+            }
+            Some(_) => {
+                while !matches!(
+                    self.peek(),
+                    None | Some(b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r')
+                ) {
+                    self.pos += 1;
+                }
+                Ok(())
+            }
+            None => Err("unexpected end of JSON".to_string()),
+        }
+    }
+
+    /// Finds the value at the index of the array starting at the current position
+    fn find_index(
+        &mut self,
+        index: usize,
+        rest: &[PathSegment],
+    ) -> Result<Option<Range<usize>>, String> {
+        if self.peek() != Some(b'[') {
+            return Ok(None);
+        }
+        self.pos += 1;
+        let mut position = 0;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b']') | None => return Ok(None),
+                Some(b',') => self.pos += 1,
+                Some(_) if position == index => return self.find(rest),
+                Some(_) => {
+                    self.skip_value()?;
+                    position += 1;
+                }
+            }
+        }
+    }
+
+    /// Finds the byte range of the value at the key path, starting at the current value
+    fn find(&mut self, path: &[PathSegment]) -> Result<Option<Range<usize>>, String> {
+        self.skip_whitespace();
+        let Some((segment, rest)) = path.split_first() else {
+            let start = self.pos;
+            self.skip_value()?;
+            return Ok(Some(start..self.pos));
+        };
+        let key = match segment {
+            PathSegment::Key(key) => key,
+            PathSegment::Index(index) => return self.find_index(*index, rest),
+        };
+        if self.peek() != Some(b'{') {
+            return Ok(None);
+        }
+        self.pos += 1;
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b'}') | None => return Ok(None),
+                Some(b',') => self.pos += 1,
+                Some(b'"') => {
+                    let key_range = self.skip_string()?;
+                    let member_key: String = serde_json::from_str(&self.text[key_range])
+                        .map_err(|err| err.to_string())?;
+                    self.skip_whitespace();
+                    if self.peek() != Some(b':') {
+                        return Err("expected ':' after JSON key".to_string());
+                    }
+                    self.pos += 1;
+                    if member_key == *key {
+                        return self.find(rest);
+                    }
+                    self.skip_value()?;
+                }
+                _ => return Err("unexpected JSON".to_string()),
+            }
+        }
+    }
+}
+
+/// Sets the string at the key path of a JSON document, keeping the rest of the document as it was
+/// A missing key is an error, it is never added
+/// # Arguments
+/// * `file` - The contents of the JSON file
+/// * `path` - The path leading to the value e.g. `packages[0].version`
+/// * `new_value` - The new string value
+/// # Returns
+/// * The new contents, or the reason the value could not be set
+pub(crate) fn set_json_string(
+    file: &str,
+    path: &[PathSegment],
+    new_value: &str,
+) -> Result<String, String> {
+    serde_json::from_str::<serde_json::Value>(file).map_err(|err| err.to_string())?;
+    let encoded = serde_json::to_string(new_value).map_err(|err| err.to_string())?;
+    let mut scanner = JsonScanner { text: file, pos: 0 };
+    match scanner.find(path)? {
+        Some(range) => Ok(splice(file, range, &encoded)),
+        None => Err(format!("missing key '{}'", display_key_path(path))),
+    }
+}
+
+/// A YAML line holding content, blank lines, comments and document markers are left out
+/// `offset` is the byte offset of the content in the file, `indent` the column it starts at
+#[derive(Debug, Clone, Copy)]
+struct YamlLine<'a> {
+    offset: usize,
+    indent: usize,
+    content: &'a str,
+}
+
+/// Splits a YAML file into its content lines
+fn yaml_lines(file: &str) -> Vec<YamlLine<'_>> {
+    let mut lines = vec![];
+    let mut line_start = 0;
+    for line in file.split_inclusive('\n') {
+        let content = line.trim_start_matches(' ');
+        let content_trimmed = content.trim_end();
+        if !(content_trimmed.is_empty()
+            || content_trimmed.starts_with('#')
+            || content_trimmed == "---"
+            || content_trimmed == "...")
+        {
+            let indent = line.len() - content.len();
+            lines.push(YamlLine {
+                offset: line_start + indent,
+                indent,
+                content,
+            });
+        }
+        line_start += line.len();
+    }
+    lines
+}
+
+/// Returns the byte offset just after the `key:` of a mapping line, if the line holds the key
+fn yaml_key_end(content: &str, key: &str) -> Option<usize> {
+    let after_key = [format!("\"{key}\""), format!("'{key}'"), key.to_string()]
+        .iter()
+        .find_map(|candidate| content.strip_prefix(candidate.as_str()))?;
+    let after_spaces = after_key.trim_start_matches(' ');
+    let rest = after_spaces.strip_prefix(':')?;
+    if rest.is_empty() || rest.starts_with([' ', '\t', '\r', '\n']) {
+        Some(content.len() - rest.len())
+    } else {
+        None
+    }
+}
+
+/// Returns the byte offset just after the `-` of a sequence item line
+fn yaml_item_end(content: &str) -> Option<usize> {
+    let rest = content.strip_prefix('-')?;
+    if rest.is_empty() || rest.starts_with([' ', '\t', '\r', '\n']) {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// Returns true if nothing but whitespace or a comment follows the offset
+fn yaml_is_empty_after(content: &str, start: usize) -> bool {
+    let rest = content[start..].trim();
+    rest.is_empty() || rest.starts_with('#')
+}
+
+/// Finds the byte range of the scalar following a `key:` or `-` and the quote it is wrapped in
+fn yaml_scalar(line: &str, start: usize) -> Result<(Range<usize>, Option<char>), String> {
+    let rest = &line[start..];
+    let mut offset = start + rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    let mut value = &line[offset..];
+    // keep the anchor, only the value after it changes
+    if value.starts_with('&') {
+        let anchor_len = value.find([' ', '\t']).unwrap_or(value.len());
+        let after_anchor = &value[anchor_len..];
+        offset +=
+            anchor_len + after_anchor.len() - after_anchor.trim_start_matches([' ', '\t']).len();
+        value = &line[offset..];
+    }
+    match value.chars().next() {
+        None | Some('#' | '\r' | '\n') => {
+            Err("the key holds a nested block, not a value".to_string())
+        }
+        Some('*') => {
+            Err("the key holds an alias, update the anchor it refers to instead".to_string())
+        }
+        Some('|' | '>' | '{' | '[') => Err("the key does not hold a plain scalar".to_string()),
+        Some(quote @ ('"' | '\'')) => {
+            let mut escaped = false;
+            let close = value[1..].char_indices().find_map(|(index, char)| {
+                let found = char == quote && !escaped;
+                escaped = quote == '"' && char == '\\' && !escaped;
+                found.then_some(index + 1)
+            });
+            match close {
+                Some(close) => Ok((offset..offset + close + 1, Some(quote))),
+                None => Err("unterminated quoted value".to_string()),
+            }
+        }
+        Some(_) => {
+            let end = value
+                .find(" #")
+                .or_else(|| value.find("\t#"))
+                .unwrap_or(value.len());
+            Ok((offset..offset + value[..end].trim_end().len(), None))
+        }
+    }
+}
+
+/// Rejects the layouts the line based editing cannot handle, a flow collection at the root
+/// e.g. `{name: app, version: 0.0.1}` and more than one document separated by `---`
+fn yaml_check_layout(file: &str) -> Result<(), String> {
+    let mut has_content = false;
+    for line in file.lines() {
+        let content = line.trim();
+        if content.is_empty() || content.starts_with('#') || content == "..." {
+            continue;
+        }
+        if content == "---" || content.starts_with("--- ") {
+            if has_content {
+                return Err("unsupported layout: the file holds more than one document".to_string());
+            }
+            continue;
+        }
+        if !has_content && content.starts_with(['{', '[']) {
+            return Err("unsupported layout: the document root is a flow collection".to_string());
+        }
+        has_content = true;
+    }
+    Ok(())
+}
+
+/// Sets the scalar at the key path of a YAML document, keeping comments, anchors and formatting
+/// A missing key is an error, as are flow mappings at the root and files holding several documents
+/// # Arguments
+/// * `file` - The contents of the YAML file
+/// * `path` - The path leading to the value e.g. `spec.template.metadata.labels.version`
+/// * `new_value` - The new value, written with the same quotes as the old one
+/// # Returns
+/// * The new contents, or the reason the value could not be set
+pub(crate) fn set_yaml_scalar(
+    file: &str,
+    path: &[PathSegment],
+    new_value: &str,
+) -> Result<String, String> {
+    yaml_check_layout(file)?;
+    serde_yaml::from_str::<serde_yaml::Value>(file).map_err(|err| err.to_string())?;
+
+    let mut block = yaml_lines(file);
+    for (depth, segment) in path.iter().enumerate() {
+        let child_indent = block.first().map(|line| line.indent).unwrap_or(0);
+        let found = match segment {
+            PathSegment::Key(key) => block.iter().enumerate().find_map(|(index, line)| {
+                (line.indent == child_indent)
+                    .then(|| yaml_key_end(line.content, key))
+                    .flatten()
+                    .map(|value_start| (index, value_start))
+            }),
+            PathSegment::Index(item) => block
+                .iter()
+                .enumerate()
+                .filter_map(|(index, line)| {
+                    (line.indent == child_indent)
+                        .then(|| yaml_item_end(line.content))
+                        .flatten()
+                        .map(|value_start| (index, value_start))
+                })
+                .nth(*item),
+        };
+        let Some((index, value_start)) = found else {
+            return Err(format!("missing key '{}'", display_key_path(path)));
+        };
+        let line = block[index];
+
+        if depth + 1 == path.len() {
+            let (range, quote) = yaml_scalar(line.content, value_start)?;
+            let replacement = match quote {
+                Some(quote) => format!("{quote}{new_value}{quote}"),
+                None => new_value.to_string(),
+            };
+            let range = line.offset + range.start..line.offset + range.end;
+            return Ok(splice(file, range, &replacement));
+        }
+
+        // the children are the lines indented past the entry, a sequence under a key may share its indent
+        let mut children = vec![];
+        if !yaml_is_empty_after(line.content, value_start) {
+            if matches!(segment, PathSegment::Key(_)) {
+                return Err(format!(
+                    "'{}' does not hold a nested block",
+                    display_key_path(&path[..=depth])
+                ));
+            }
+            // an item like `- name: app` starts its mapping on the same line
+            let rest = &line.content[value_start..];
+            let spaces = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+            children.push(YamlLine {
+                offset: line.offset + value_start + spaces,
+                indent: line.indent + value_start + spaces,
+                content: &rest[spaces..],
+            });
+        }
+        let compact_sequence = matches!(segment, PathSegment::Key(_));
+        children.extend(block[index + 1..].iter().take_while(|child| {
+            child.indent > child_indent
+                || (compact_sequence
+                    && child.indent == child_indent
+                    && yaml_item_end(child.content).is_some())
+        }));
+        block = children;
+    }
+    Err("an empty key path cannot be set".to_string())
+}
+
+/// Replaces the byte range of the text
+fn splice(text: &str, range: Range<usize>, replacement: &str) -> String {
+    let mut result = String::with_capacity(text.len() + replacement.len());
+    result.push_str(&text[..range.start]);
+    result.push_str(replacement);
+    result.push_str(&text[range.end..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(key_path: &str) -> Vec<PathSegment> {
+        parse_key_path(key_path).unwrap()
+    }
+
+    #[test]
+    fn test_parse_key_path() {
+        assert_eq!(
+            path("spec.containers[0].image"),
+            vec![
+                PathSegment::Key("spec".to_string()),
+                PathSegment::Key("containers".to_string()),
+                PathSegment::Index(0),
+                PathSegment::Key("image".to_string()),
+            ]
+        );
+        assert_eq!(display_key_path(&path("a[1][2].b")), "a[1][2].b");
+        assert_eq!(
+            path(r#"metadata.labels."app.kubernetes.io/version""#),
+            vec![
+                PathSegment::Key("metadata".to_string()),
+                PathSegment::Key("labels".to_string()),
+                PathSegment::Key("app.kubernetes.io/version".to_string()),
+            ]
+        );
+        assert_eq!(
+            path(r"labels.app\.kubernetes\.io/version"),
+            path(r#"labels."app.kubernetes.io/version""#)
+        );
+        assert_eq!(
+            path(r#""a\"b"[0]"#),
+            vec![PathSegment::Key("a\"b".to_string()), PathSegment::Index(0)]
+        );
+        assert_eq!(
+            display_key_path(&path(r"labels.app\.kubernetes\.io/version")),
+            r#"labels."app.kubernetes.io/version""#
+        );
+        for invalid in [
+            "", "a..b", "a[x]", "a[0", "a[0]b", "a.", r#""a"#, r#""a"b"#, r"a\",
+        ] {
+            assert!(parse_key_path(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_set_json_string_keeps_formatting() {
+        let original = "{\n\t\"name\": \"app\",\n\t\"version\": \"0.0.1\",\n\t\"scripts\": {\"version\": \"echo\"}\n}\n";
+        let updated = set_json_string(original, &path("version"), "0.0.2").unwrap();
+        assert_eq!(updated, original.replace("0.0.1", "0.0.2"));
+
+        let nested = set_json_string(original, &path("scripts.version"), "0.0.2").unwrap();
+        assert_eq!(nested, original.replace("\"echo\"", "\"0.0.2\""));
+    }
+
+    #[test]
+    fn test_set_json_string_reports_missing_key() {
+        let original = "{\n    \"name\": \"app\"\n}\n";
+        assert_eq!(
+            set_json_string(original, &path("version"), "0.0.2").unwrap_err(),
+            "missing key 'version'"
+        );
+        assert_eq!(
+            set_json_string("{}", &path("package.version"), "0.0.2").unwrap_err(),
+            "missing key 'package.version'"
+        );
+    }
+
+    #[test]
+    fn test_set_yaml_scalar_keeps_comments_and_anchors() {
+        let original = r#"# chart metadata
+apiVersion: v2
+name: app
+version: 0.0.1 # chart version
+appVersion: &app "0.0.1"
+image:
+  tag: *app
+  labels:
+    version: '0.0.1'
+"#;
+        let updated = set_yaml_scalar(original, &path("version"), "0.0.2").unwrap();
+        assert_eq!(
+            updated,
+            original.replace("version: 0.0.1 #", "version: 0.0.2 #")
+        );
+
+        let anchored = set_yaml_scalar(original, &path("appVersion"), "0.0.2").unwrap();
+        assert_eq!(
+            anchored,
+            original.replace("&app \"0.0.1\"", "&app \"0.0.2\"")
+        );
+
+        let nested = set_yaml_scalar(original, &path("image.labels.version"), "0.0.2").unwrap();
+        assert_eq!(nested, original.replace("'0.0.1'", "'0.0.2'"));
+
+        assert!(set_yaml_scalar(original, &path("image.tag"), "0.0.2").is_err());
+    }
+
+    #[test]
+    fn test_set_yaml_scalar_reports_missing_key() {
+        assert_eq!(
+            set_yaml_scalar("name: app", &path("version"), "0.0.2").unwrap_err(),
+            "missing key 'version'"
+        );
+        assert_eq!(
+            set_yaml_scalar("{name: app}", &path("version"), "0.0.2").unwrap_err(),
+            "unsupported layout: the document root is a flow collection"
+        );
+        assert_eq!(
+            set_yaml_scalar(
+                "version: 0.0.1\n---\nversion: 0.0.1\n",
+                &path("version"),
+                "0.0.2"
+            )
+            .unwrap_err(),
+            "unsupported layout: the file holds more than one document"
+        );
+        // a single document may still start with a marker
+        assert_eq!(
+            set_yaml_scalar("---\nversion: 0.0.1\n", &path("version"), "0.0.2").unwrap(),
+            "---\nversion: 0.0.2\n"
+        );
+    }
+
+    #[test]
+    fn test_set_json_string_in_array() {
+        let original = "{\"packages\": [{\"version\": \"0.0.1\"}, {\"version\": \"0.0.1\"}]}";
+        let updated = set_json_string(original, &path("packages[1].version"), "0.0.2").unwrap();
+        assert_eq!(
+            updated,
+            "{\"packages\": [{\"version\": \"0.0.1\"}, {\"version\": \"0.0.2\"}]}"
+        );
+        assert!(set_json_string(original, &path("packages[2].version"), "0.0.2").is_err());
+    }
+
+    #[test]
+    fn test_set_yaml_scalar_in_sequences() {
+        let original = r#"spec:
+  template:
+    metadata:
+      labels:
+        version: "0.0.1"
+    containers:
+    - name: app
+      image: app:0.0.1
+    - name: sidecar
+      env:
+        - 0.0.1
+        - 0.0.1
+"#;
+        let label = set_yaml_scalar(
+            original,
+            &path("spec.template.metadata.labels.version"),
+            "0.0.2",
+        )
+        .unwrap();
+        assert_eq!(label, original.replace("\"0.0.1\"", "\"0.0.2\""));
+
+        let image = set_yaml_scalar(
+            original,
+            &path("spec.template.containers[0].image"),
+            "app:0.0.2",
+        )
+        .unwrap();
+        assert_eq!(image, original.replace("app:0.0.1", "app:0.0.2"));
+
+        let env = set_yaml_scalar(
+            original,
+            &path("spec.template.containers[1].env[1]"),
+            "0.0.2",
+        )
+        .unwrap();
+        assert_eq!(
+            env,
+            original.replace("- 0.0.1\n        - 0.0.1", "- 0.0.1\n        - 0.0.2")
+        );
+
+        assert!(
+            set_yaml_scalar(original, &path("spec.template.containers[2].image"), "x").is_err()
+        );
+        assert!(set_yaml_scalar(original, &path("spec.replicas"), "x").is_err());
+    }
+
+    #[test]
+    fn test_set_dotted_keys() {
+        let original = "metadata:\n  labels:\n    app.kubernetes.io/name: app\n    app.kubernetes.io/version: \"0.0.1\"\n";
+        let updated = set_yaml_scalar(
+            original,
+            &path(r#"metadata.labels."app.kubernetes.io/version""#),
+            "0.0.2",
+        )
+        .unwrap();
+        assert_eq!(updated, original.replace("0.0.1", "0.0.2"));
+
+        let original = "{\"labels\": {\"app.kubernetes.io/version\": \"0.0.1\"}}";
+        let updated = set_json_string(
+            original,
+            &path(r"labels.app\.kubernetes\.io/version"),
+            "0.0.2",
+        )
+        .unwrap();
+        assert_eq!(updated, original.replace("0.0.1", "0.0.2"));
+    }
+}
@@ -0,0 +1,160 @@
+use std::fmt;
+
+/// The crate-wide result type
+pub type Result<T> = std::result::Result<T, GitScribeError>;
+
+/// Enum representing every error gitscribe can return
+///
+/// Each category maps to its own process exit code, see `exit_code`
+/// # Variants
+/// * `Git` - a git command failed to run or exited unsuccessfully
+/// * `UncommittedChanges` - the working tree has changes, each entry is a `git status --porcelain` line
+/// * `Config` - the config file is missing, unreadable or invalid
+/// * `Parse` - a version, commit or other value could not be parsed
+/// * `Template` - a changelog template failed to render
+/// * `Io` - reading or writing a file or the terminal failed
+/// * `SyncFile` - a version sync file could not be read or updated
+/// * `VersionDrift` - version sync files hold a different version than the config, each entry is a `path (key)`
+/// * `BranchPolicy` - the release is not allowed on the current branch
+/// * `Hook` - a release hook command failed, the release was aborted
+#[derive(Debug)]
+pub enum GitScribeError {
+    Git(String),
+    UncommittedChanges(Vec<String>),
+    Config(String),
+    Parse(String),
+    Template(askama::Error),
+    Io(std::io::Error),
+    SyncFile { file_path: String, reason: String },
+    VersionDrift(Vec<String>),
+    BranchPolicy(String),
+    Hook(String),
+}
+
+impl GitScribeError {
+    /// Builds a sync file error for the given file
+    pub fn sync_file(file_path: impl Into<String>, reason: impl fmt::Display) -> Self {
+        GitScribeError::SyncFile {
+            file_path: file_path.into(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// The process exit code for the error category
+    ///
+    /// | Code | Category |
+    /// |------|----------|
+    /// | 3 | git, including uncommitted changes |
+    /// | 4 | config |
+    /// | 5 | parse |
+    /// | 6 | template |
+    /// | 7 | io |
+    /// | 8 | sync file |
+    /// | 9 | version drift |
+    /// | 10 | branch policy |
+    /// | 11 | hook |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            GitScribeError::Git(_) | GitScribeError::UncommittedChanges(_) => 3,
+            GitScribeError::Config(_) => 4,
+            GitScribeError::Parse(_) => 5,
+            GitScribeError::Template(_) => 6,
+            GitScribeError::Io(_) => 7,
+            GitScribeError::SyncFile { .. } => 8,
+            GitScribeError::VersionDrift(_) => 9,
+            GitScribeError::BranchPolicy(_) => 10,
+            GitScribeError::Hook(_) => 11,
+        }
+    }
+}
+
+impl fmt::Display for GitScribeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GitScribeError::Git(msg) => write!(f, "git error: {msg}"),
+            GitScribeError::UncommittedChanges(changes) => write!(
+                f,
+                "there are {} uncommitted changes, please commit before trying again",
+                changes.len()
+            ),
+            GitScribeError::Config(msg) => write!(f, "config error: {msg}"),
+            GitScribeError::Parse(msg) => write!(f, "parse error: {msg}"),
+            GitScribeError::Template(err) => write!(f, "template error: {err}"),
+            GitScribeError::Io(err) => write!(f, "io error: {err}"),
+            GitScribeError::SyncFile { file_path, reason } => {
+                write!(f, "failed to sync version in {file_path}: {reason}")
+            }
+            GitScribeError::VersionDrift(files) => write!(
+                f,
+                "{} version sync files do not match the configured version: {}",
+                files.len(),
+                files.join(", ")
+            ),
+            GitScribeError::BranchPolicy(msg) => write!(f, "branch policy: {msg}"),
+            GitScribeError::Hook(msg) => write!(f, "hook failed: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for GitScribeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GitScribeError::Template(err) => Some(err),
+            GitScribeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for GitScribeError {
+    fn from(err: std::io::Error) -> Self {
+        GitScribeError::Io(err)
+    }
+}
+
+impl From<askama::Error> for GitScribeError {
+    fn from(err: askama::Error) -> Self {
+        GitScribeError::Template(err)
+    }
+}
+
+impl From<inquire::InquireError> for GitScribeError {
+    fn from(err: inquire::InquireError) -> Self {
+        match err {
+            inquire::InquireError::IO(err) => GitScribeError::Io(err),
+            inquire::InquireError::NotTTY => GitScribeError::Io(std::io::Error::other(
+                "input is not a terminal, pass --yes and the answers as flags to run without prompts",
+            )),
+            err => GitScribeError::Io(std::io::Error::other(err.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_distinct_per_category() {
+        let errors = [
+            GitScribeError::Git("".to_string()),
+            GitScribeError::Config("".to_string()),
+            GitScribeError::Parse("".to_string()),
+            GitScribeError::Template(askama::Error::Fmt(fmt::Error)),
+            GitScribeError::Io(std::io::Error::other("")),
+            GitScribeError::sync_file("Cargo.toml", ""),
+            GitScribeError::VersionDrift(vec![]),
+            GitScribeError::BranchPolicy("".to_string()),
+            GitScribeError::Hook("".to_string()),
+        ];
+        let mut codes = errors.iter().map(|err| err.exit_code()).collect::<Vec<_>>();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+        assert!(!codes.contains(&0) && !codes.contains(&1) && !codes.contains(&2));
+        assert_eq!(
+            GitScribeError::UncommittedChanges(vec![]).exit_code(),
+            GitScribeError::Git("".to_string()).exit_code()
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use crate::error::{GitScribeError, Result};
+
+/// Runs a git command in the current directory
+/// # Arguments
+/// * `args` - The arguments passed to git
+/// # Returns
+/// * The stdout of the command
+/// # Errors
+/// * A git error holding git's stderr if the command cannot be run or exits unsuccessfully
+pub fn run_git(args: &[&str]) -> Result<String> {
+    run_git_with_env(args, &[]).map(|(stdout, _)| stdout)
+}
+
+/// Runs a git command in the current directory with extra environment variables, keeping what it wrote to stderr
+/// # Arguments
+/// * `args` - The arguments passed to git
+/// * `env` - The environment variables set for the command e.g. `GIT_COMMITTER_NAME`
+/// # Returns
+/// * The stdout and stderr of the command, git reports progress and warnings on stderr even when it succeeds
+/// # Errors
+/// * A git error holding the exit status and git's stderr, or stdout when stderr is empty
+///   as some commands like `git commit` explain why they failed there
+pub fn run_git_with_env(args: &[&str], env: &[(String, String)]) -> Result<(String, String)> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .output()
+        .map_err(|err| GitScribeError::Git(format!("Failed to execute git: {err}")))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    if output.status.success() {
+        Ok((stdout, stderr))
+    } else {
+        let reason = match stderr.trim() {
+            "" => stdout.trim(),
+            stderr => stderr,
+        };
+        Err(GitScribeError::Git(format!(
+            "`git {}` exited with {}: {}",
+            args.join(" "),
+            output.status,
+            reason
+        )))
+    }
+}
+
+/// The name of the checked out branch
+/// # Returns
+/// * None when HEAD is detached
+/// # Errors
+/// * A git error if git fails
+pub fn current_branch() -> Result<Option<String>> {
+    let branch = run_git(&["branch", "--show-current"])?.trim().to_string();
+    Ok((!branch.is_empty()).then_some(branch))
+}
+
+/// The commit HEAD points to
+/// # Returns
+/// * None when the branch has no commits yet
+pub fn head_commit() -> Option<String> {
+    run_git(&["rev-parse", "--verify", "--quiet", "HEAD"])
+        .ok()
+        .map(|commit| commit.trim().to_string())
+}
+
+/// The tracked paths changed in the working tree or the index since HEAD, untracked files are left out
+/// # Returns
+/// * The paths relative to the root of the repo
+/// # Errors
+/// * A git error if git fails or HEAD has no commit
+pub fn modified_paths() -> Result<Vec<String>> {
+    let diff = run_git(&["diff", "--name-only", "-z", "HEAD"])?;
+    Ok(diff
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(|path| path.to_string())
+        .collect())
+}
+
+/// The paths with changes in the working tree or the index, including untracked files
+/// # Returns
+/// * The paths relative to the root of the repo, both sides of a rename
+/// # Errors
+/// * A git error if git fails
+pub fn changed_paths() -> Result<Vec<String>> {
+    let status = run_git(&["status", "--porcelain", "-z", "--untracked-files=all"])?;
+    let mut paths = vec![];
+    let mut entries = status.split('\0').filter(|entry| !entry.is_empty());
+    while let Some(entry) = entries.next() {
+        let Some((code, path)) = entry.split_at_checked(3) else {
+            continue;
+        };
+        paths.push(path.to_string());
+        // a rename or copy is followed by the path it came from
+        if code.starts_with(['R', 'C']) {
+            if let Some(source) = entries.next() {
+                paths.push(source.to_string());
+            }
+        }
+    }
+    Ok(paths)
+}
@@ -0,0 +1,199 @@
+//! Release hooks, shell commands run at fixed stages of a release
+//!
+//! Each stage lists the commands to run, one after another, e.g. `cargo build` after the version
+//! files are synced so Cargo.lock is up to date. The commands run in the root of the repo through
+//! `sh -c`, or `cmd /C` on Windows, and learn about the release from `GITSCRIBE_*` environment variables.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{GitScribeError, Result};
+
+/// The stages of a release hooks run at, in the order they run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookStage {
+    /// Before any file is written, the commands can still change the files the release reads
+    PreBump,
+    /// After gitscribe.json and the version sync files are written
+    PostSync,
+    /// After the changelogs are written
+    PostChangelog,
+    /// Right before the release commit
+    PreCommit,
+    /// After the release commit
+    PostCommit,
+    /// After the release is tagged and branched, before it is pushed
+    PostTag,
+}
+
+impl fmt::Display for HookStage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let stage = match self {
+            HookStage::PreBump => "pre-bump",
+            HookStage::PostSync => "post-sync",
+            HookStage::PostChangelog => "post-changelog",
+            HookStage::PreCommit => "pre-commit",
+            HookStage::PostCommit => "post-commit",
+            HookStage::PostTag => "post-tag",
+        };
+        write!(f, "{stage}")
+    }
+}
+
+/// Struct Representing the commands run at each stage of a release
+///
+/// # Fields
+///
+/// * `pre_bump` - run before any file is written e.g. `cargo test`
+/// * `post_sync` - run after the version files are written e.g. `cargo build` to update Cargo.lock
+/// * `post_changelog` - run after the changelogs are written e.g. `npx prettier --write CHANGELOG.md`
+/// * `pre_commit` - run right before the release commit
+/// * `post_commit` - run after the release commit
+/// * `post_tag` - run after the release is tagged, before it is pushed e.g. an artifact upload
+///
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ReleaseHooks {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_bump: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_sync: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_changelog: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pre_commit: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_commit: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub post_tag: Vec<String>,
+}
+
+impl ReleaseHooks {
+    /// The commands of a stage
+    pub fn commands(&self, stage: HookStage) -> &[String] {
+        match stage {
+            HookStage::PreBump => &self.pre_bump,
+            HookStage::PostSync => &self.post_sync,
+            HookStage::PostChangelog => &self.post_changelog,
+            HookStage::PreCommit => &self.pre_commit,
+            HookStage::PostCommit => &self.post_commit,
+            HookStage::PostTag => &self.post_tag,
+        }
+    }
+
+    /// Returns true if no stage has a command
+    pub fn is_empty(&self) -> bool {
+        self.pre_bump.is_empty()
+            && self.post_sync.is_empty()
+            && self.post_changelog.is_empty()
+            && self.pre_commit.is_empty()
+            && self.post_commit.is_empty()
+            && self.post_tag.is_empty()
+    }
+}
+
+/// What a hook is told about the release
+/// # Fields
+/// * `version` - The new version, for packages their tags e.g. `api@1.2.0, web@0.3.1`
+/// * `previous_version` - The same for the versions before the release
+/// * `package` - The names of the released packages, empty for the root version
+/// * `notes_file` - The changelog file the release writes, if any
+#[derive(Debug, Clone, Default)]
+pub struct HookContext {
+    pub version: String,
+    pub previous_version: String,
+    pub package: String,
+    pub notes_file: Option<String>,
+}
+
+/// Runs a hook command with the release in its environment
+///
+/// The environment holds `GITSCRIBE_HOOK`, `GITSCRIBE_VERSION`, `GITSCRIBE_PREVIOUS_VERSION`, `GITSCRIBE_PACKAGE`,
+/// `GITSCRIBE_NOTES_FILE` and `GITSCRIBE_CHANGED_FILES`, the files changed so far one per line
+/// # Arguments
+/// * `command` - The shell command to run
+/// * `stage` - The stage the command runs at
+/// * `context` - The release the command runs for
+/// * `changed_files` - The files the release has changed so far
+/// # Errors
+/// * A hook error if the command cannot be started or exits unsuccessfully
+/// # Examples
+/// ```no_run
+/// use gitscribe::hooks::{run_hook, HookContext, HookStage};
+/// let context = HookContext {
+///     version: "1.2.0".to_string(),
+///     previous_version: "1.1.0".to_string(),
+///     ..HookContext::default()
+/// };
+/// run_hook("cargo build", HookStage::PostSync, &context, &["Cargo.toml".to_string()]).unwrap();
+/// ```
+pub fn run_hook(
+    command: &str,
+    stage: HookStage,
+    context: &HookContext,
+    changed_files: &[String],
+) -> Result<()> {
+    let mut shell = match cfg!(windows) {
+        true => {
+            let mut shell = std::process::Command::new("cmd");
+            shell.arg("/C");
+            shell
+        }
+        false => {
+            let mut shell = std::process::Command::new("sh");
+            shell.arg("-c");
+            shell
+        }
+    };
+    let status = shell
+        .arg(command)
+        .env("GITSCRIBE_HOOK", stage.to_string())
+        .env("GITSCRIBE_VERSION", &context.version)
+        .env("GITSCRIBE_PREVIOUS_VERSION", &context.previous_version)
+        .env("GITSCRIBE_PACKAGE", &context.package)
+        .env(
+            "GITSCRIBE_NOTES_FILE",
+            context.notes_file.clone().unwrap_or_default(),
+        )
+        .env("GITSCRIBE_CHANGED_FILES", changed_files.join("\n"))
+        .status()
+        .map_err(|err| {
+            GitScribeError::Hook(format!(
+                "`{command}` could not be run in the {stage} hook: {err}"
+            ))
+        })?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(GitScribeError::Hook(format!(
+            "`{command}` exited with {status} in the {stage} hook"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_hook_with_release_environment() {
+        let context = HookContext {
+            version: "1.2.0".to_string(),
+            previous_version: "1.1.0".to_string(),
+            package: String::new(),
+            notes_file: Some("CHANGELOG.md".to_string()),
+        };
+        let changed_files = ["gitscribe.json".to_string(), "Cargo.toml".to_string()];
+        run_hook(
+            r#"test "$GITSCRIBE_HOOK $GITSCRIBE_VERSION $GITSCRIBE_PREVIOUS_VERSION $GITSCRIBE_NOTES_FILE" = "post-sync 1.2.0 1.1.0 CHANGELOG.md" && test "$(echo "$GITSCRIBE_CHANGED_FILES" | wc -l)" -eq 2"#,
+            HookStage::PostSync,
+            &context,
+            &changed_files,
+        )
+        .unwrap();
+
+        let err = run_hook("exit 3", HookStage::PreCommit, &context, &[]).unwrap_err();
+        assert!(matches!(err, GitScribeError::Hook(_)));
+        assert!(err.to_string().contains("pre-commit"));
+    }
+}
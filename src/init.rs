@@ -0,0 +1,490 @@
+use colored::Colorize;
+use inquire::{
+    list_option::ListOption,
+    validator::{Validation, ValueRequiredValidator},
+    Text,
+};
+use std::{fs::OpenOptions, io::Write, path::Path};
+
+use crate::{
+    changelog::TemplateOption,
+    config::{ChangelogOutputOption, Config},
+    detect::{detect_version_sync_files, mismatched_versions, DetectedSyncFile},
+    error::{GitScribeError, Result},
+    version::Version,
+    version_file_sync::{SupportedSyncFileFormat, VersionSyncFile},
+    EXPECTED_CONFIG_FILE_NAME,
+};
+
+/// The commands that can create a release branch, in the order they are offered
+const RELEASE_COMMANDS: [&str; 3] = ["major", "minor", "patch"];
+
+/// Enum representing the project presets that pre-fill the version sync files
+/// # Variants
+/// * `Rust` - syncs `./Cargo.toml`
+/// * `Node` - syncs `./package.json`
+/// * `Python` - syncs `./pyproject.toml`, as a Poetry file if it has `[tool.poetry].version` and PEP 621 otherwise
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Preset {
+    Rust,
+    Node,
+    Python,
+}
+impl Preset {
+    pub fn values() -> Vec<Self> {
+        vec![Self::Rust, Self::Node, Self::Python]
+    }
+
+    /// The version sync files the preset adds
+    pub fn version_sync_files(&self) -> Vec<VersionSyncFile> {
+        let (file_format, file_path) = match self {
+            Preset::Rust => (SupportedSyncFileFormat::CargoToml, "./Cargo.toml"),
+            Preset::Node => (SupportedSyncFileFormat::Node, "./package.json"),
+            Preset::Python => (
+                python_sync_format(std::fs::read_to_string("./pyproject.toml").ok().as_deref()),
+                "./pyproject.toml",
+            ),
+        };
+        vec![VersionSyncFile {
+            file_format,
+            file_path: file_path.to_string(),
+            version_key: "version".to_string(),
+            additional_version_keys: vec![],
+        }]
+    }
+}
+/// The format of a pyproject.toml, Poetry only when the version is under `[tool.poetry]`
+/// # Arguments
+/// * `contents` - The contents of the pyproject.toml, if there is one
+fn python_sync_format(contents: Option<&str>) -> SupportedSyncFileFormat {
+    let poetry = contents
+        .and_then(|contents| contents.parse::<toml::Table>().ok())
+        .is_some_and(|pyproject| {
+            pyproject
+                .get("tool")
+                .and_then(|tool| tool.get("poetry"))
+                .and_then(|poetry| poetry.get("version"))
+                .is_some()
+        });
+    match poetry {
+        true => SupportedSyncFileFormat::PoetryToml,
+        false => SupportedSyncFileFormat::PyprojectToml,
+    }
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Preset::Rust => write!(f, "rust"),
+            Preset::Node => write!(f, "node"),
+            Preset::Python => write!(f, "python"),
+        }
+    }
+}
+impl std::str::FromStr for Preset {
+    type Err = GitScribeError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Self::values()
+            .into_iter()
+            .find(|preset| preset.to_string().eq_ignore_ascii_case(value.trim()))
+            .ok_or_else(|| {
+                GitScribeError::Parse(format!(
+                    "Unknown preset '{value}', expected one of: rust, node, python"
+                ))
+            })
+    }
+}
+
+/// Answers for the init prompts, every answer that is set skips its prompt
+///
+/// # Fields
+///
+/// * `version` - the initial version
+/// * `repo` - the project repository
+/// * `templates` - the changelog outputs
+/// * `sync_files` - the version sync files, added to the ones from the preset
+/// * `branch_for_release` - the commands that create a release branch, setting it turns branching on
+/// * `preset` - the OPTIONAL project preset which pre-fills the version sync files
+/// * `yes` - accept the default for every answer that is not set instead of prompting
+/// * `no_detect` - skip scanning the project for manifests to offer as version sync files
+#[derive(Debug, Clone, Default)]
+pub struct InitOptions {
+    pub version: Option<String>,
+    pub repo: Option<String>,
+    pub templates: Vec<ChangelogOutputOption>,
+    pub sync_files: Vec<VersionSyncFile>,
+    pub branch_for_release: Option<Vec<String>>,
+    pub preset: Option<Preset>,
+    pub yes: bool,
+    pub no_detect: bool,
+}
+
+/// Handles the initialization of the config file
+/// # Arguments
+/// * `options` - The answers given on the command line
+/// # Errors
+/// * A config error if the config file already exists
+/// * A parse error if an answer is invalid
+/// * An io error if a prompt fails or the config file cannot be written
+pub fn handle_init(options: InitOptions) -> Result<()> {
+    if std::fs::read_to_string(EXPECTED_CONFIG_FILE_NAME).is_ok() {
+        return Err(GitScribeError::Config(format!(
+            "Config file already exists: {}. Please remove it if you want to reinitialize.",
+            EXPECTED_CONFIG_FILE_NAME
+        )));
+    }
+
+    let config = build_config(&options)?;
+
+    // create the file
+    let config_file = serde_json::to_string_pretty(&config)
+        .map_err(|err| GitScribeError::Config(format!("Failed to serialize config: {err}")))?;
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(EXPECTED_CONFIG_FILE_NAME)?;
+    file.write_all(config_file.as_bytes())?;
+    println!("{}", "🚀Config file has been initialized.".green());
+    Ok(())
+}
+
+/// Builds the config from the options, prompting for anything they do not answer
+/// When `options.yes` is set nothing is prompted, so it is safe to call without a TTY
+/// # Arguments
+/// * `options` - The answers given on the command line
+pub fn build_config(options: &InitOptions) -> Result<Config> {
+    let mut config = Config::create_default();
+    let mut version_sync_files = options
+        .preset
+        .as_ref()
+        .map(|preset| preset.version_sync_files())
+        .unwrap_or_default();
+    version_sync_files.extend(options.sync_files.iter().cloned());
+    // only look for manifests when the sync files were not given
+    let detected = if version_sync_files.is_empty() && !options.no_detect {
+        detect_version_sync_files(Path::new("."))
+    } else {
+        vec![]
+    };
+    // default to the version the project already uses
+    if let Some(detected) = detected.first() {
+        if Version::new(detected.current_version.clone()).is_ok() {
+            config.version = detected.current_version.clone();
+        }
+    }
+
+    // walk through the config file creation
+    config.version = match (&options.version, options.yes) {
+        (Some(version), _) => version.clone(),
+        (None, true) => config.version.clone(),
+        (None, false) => Text::new("Enter the initial version")
+            .with_help_message("The initial version of the application")
+            .with_default(config.version.clone().as_str())
+            .prompt()?,
+    };
+    Version::new(config.version.clone())?;
+
+    // handle the project repo, defaults to None
+    let project_repo = match (&options.repo, options.yes) {
+        (Some(repo), _) => repo.clone(),
+        (None, true) => "".to_string(),
+        (None, false) => Text::new("Enter the project repository")
+            .with_help_message("eg. https://github.com/mikaelmello/inquire/")
+            .with_default("")
+            .prompt()?,
+    };
+    if !project_repo.is_empty() {
+        config.project_repo = Some(project_repo);
+    }
+
+    match (&options.branch_for_release, options.yes) {
+        (Some(commands), _) => {
+            validate_release_commands(commands)?;
+            config.branch_for_release = true;
+            config.commands_that_release = commands.clone();
+        }
+        (None, true) => {}
+        (None, false) => {
+            config.branch_for_release = inquire::Confirm::new("Create a branch for releases?")
+                .with_help_message("'y' for yes or 'n' for no")
+                .prompt()?;
+            if config.branch_for_release {
+                config.commands_that_release = inquire::MultiSelect::new(
+                    "Select Commands That Create A Release",
+                    RELEASE_COMMANDS
+                        .iter()
+                        .map(|command| command.to_string())
+                        .collect(),
+                )
+                .with_default(&[0, 1]) //default to major and minor
+                .with_validator(|input: &[ListOption<&String>]| {
+                    // ensure major is selected
+                    if input.iter().any(|option| option.value == "major") {
+                        Ok(Validation::Valid)
+                    } else {
+                        Ok(Validation::Invalid("Major must be selected".into()))
+                    }
+                })
+                .prompt()?;
+            }
+        }
+    }
+
+    // handle the changelog output selections
+    if !options.templates.is_empty() {
+        config.changelog_output_selections = options.templates.clone();
+    } else if !options.yes {
+        config.changelog_output_selections = prompt_changelog_outputs()?;
+    }
+
+    if version_sync_files.is_empty() {
+        warn_mismatched_versions(&detected, &config.version);
+        version_sync_files = if options.yes || detected.is_empty() {
+            detected
+                .into_iter()
+                .map(|detected| detected.version_sync_file)
+                .collect()
+        } else {
+            let all = (0..detected.len()).collect::<Vec<usize>>();
+            inquire::MultiSelect::new("Select The Detected Files To Sync", detected)
+                .with_default(&all)
+                .prompt()?
+                .into_iter()
+                .map(|detected| detected.version_sync_file)
+                .collect()
+        };
+        if !options.yes {
+            version_sync_files.extend(prompt_version_sync_files()?.unwrap_or_default());
+        }
+    }
+    if !version_sync_files.is_empty() {
+        config.version_sync_files = Some(version_sync_files);
+    }
+
+    Ok(config)
+}
+
+/// Prints a warning for each detected file whose version differs from the configured version
+fn warn_mismatched_versions(detected: &[DetectedSyncFile], version: &str) {
+    let mismatched = mismatched_versions(detected, version);
+    if mismatched.is_empty() {
+        return;
+    }
+    println!(
+        "{}",
+        format!("⚠️ These files do not hold the version {version}, the next release will overwrite them:")
+            .yellow()
+    );
+    for detected in mismatched {
+        println!("{}", format!("    {detected}").yellow());
+    }
+}
+
+/// Ensures every release command is known and that major is one of them
+fn validate_release_commands(commands: &[String]) -> Result<()> {
+    if let Some(command) = commands
+        .iter()
+        .find(|command| !RELEASE_COMMANDS.contains(&command.as_str()))
+    {
+        return Err(GitScribeError::Parse(format!(
+            "Unknown release command '{command}', expected major, minor or patch"
+        )));
+    }
+    if !commands.iter().any(|command| command == "major") {
+        return Err(GitScribeError::Parse(
+            "Major must be one of the commands that create a release branch".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Prompts for the changelog output files
+fn prompt_changelog_outputs() -> Result<Vec<ChangelogOutputOption>> {
+    let mut changelog_output_selections = vec![];
+    println!(
+        "{}",
+        "----------------- Output Files -----------------".green()
+    );
+
+    loop {
+        let template_option =
+            inquire::Select::new("Select A Changelog Template", TemplateOption::values())
+                .prompt()?;
+
+        let default_filepath = match template_option {
+            TemplateOption::Markdown => "./CHANGELOG.md",
+            TemplateOption::VueQuasar => "./src/components/GitscribeChangelog.vue",
+        };
+        let output_filepath = Text::new("Enter the output filepath")
+            .with_help_message("The output filepath relative to the root of the project")
+            .with_validator(ValueRequiredValidator::new(
+                "The output filepath cannot be empty",
+            ))
+            .with_default(default_filepath)
+            .prompt()?;
+
+        changelog_output_selections.push(ChangelogOutputOption {
+            template_option,
+            output_filepath,
+        });
+        let add_another = inquire::Confirm::new("Add another changelog file output?")
+            .with_help_message("'y' for yes or 'n' for no")
+            .prompt()?;
+        if !add_another {
+            return Ok(changelog_output_selections);
+        }
+    }
+}
+
+/// Prompts for the version sync files, returns None if the user does not want any
+fn prompt_version_sync_files() -> Result<Option<Vec<VersionSyncFile>>> {
+    let add_version_sync_files = inquire::Confirm::new("Add a file to sync the version with?")
+        .with_help_message("'y' for yes or 'n' for no")
+        .prompt()?;
+    if !add_version_sync_files {
+        return Ok(None);
+    }
+    println!(
+        "{}",
+        "----------------- Files To Sync -----------------".green()
+    );
+    // handle the version sync files
+    let mut version_sync_files = vec![];
+    loop {
+        let file_path = Text::new("Enter the file path")
+            .with_help_message("The file path relative to the root of the project")
+            .with_validator(ValueRequiredValidator::new("The file path cannot be empty"))
+            .prompt()?;
+        let file_format =
+            inquire::Select::new("Select A File Format", SupportedSyncFileFormat::values())
+                .prompt()?;
+        let version_sync_file = match file_format {
+            SupportedSyncFileFormat::Regex => {
+                let pattern = Text::new("Enter the pattern")
+                    .with_help_message(
+                        "A regex whose first capture group, or group named version, holds the version. e.g. __version__ = \"(.+)\"",
+                    )
+                    .with_validator(ValueRequiredValidator::new("The pattern cannot be empty"))
+                    .prompt()?;
+                VersionSyncFile {
+                    file_path,
+                    file_format,
+                    version_key: pattern,
+                    additional_version_keys: vec![],
+                }
+            }
+            SupportedSyncFileFormat::PlainText => VersionSyncFile {
+                file_path,
+                file_format,
+                version_key: "version".to_string(),
+                additional_version_keys: vec![],
+            },
+            _ => {
+                let version_keys = Text::new("Enter the version key")
+                    .with_help_message(
+                        "The keys in the file to update with the new version, separated by commas. e.g. version or spec.labels.version,appVersion",
+                    )
+                    .with_default("version")
+                    .prompt()?;
+                let mut version_keys = version_keys
+                    .split(',')
+                    .map(|key| key.trim().to_string())
+                    .filter(|key| !key.is_empty());
+                VersionSyncFile {
+                    file_path,
+                    file_format,
+                    version_key: version_keys.next().unwrap_or_else(|| "version".to_string()),
+                    additional_version_keys: version_keys.collect(),
+                }
+            }
+        };
+        version_sync_files.push(version_sync_file);
+        let add_another = inquire::Confirm::new("Add another version sync file?")
+            .with_help_message(
+                "If you want to add another version sync file, select yes. Otherwise, select no.",
+            )
+            .prompt()?;
+        if !add_another {
+            return Ok(Some(version_sync_files));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_config_with_yes_uses_defaults() {
+        let config = build_config(&InitOptions {
+            yes: true,
+            no_detect: true,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(config.version, "0.0.1");
+        assert_eq!(config.project_repo, None);
+        assert!(!config.branch_for_release);
+        assert_eq!(config.changelog_output_selections.len(), 1);
+        assert!(config.version_sync_files.is_none());
+    }
+
+    #[test]
+    fn test_build_config_from_flags() {
+        let config = build_config(&InitOptions {
+            version: Some("1.2.3".to_string()),
+            repo: Some("https://github.com/Kade-Powell/gitscribe".to_string()),
+            templates: vec!["vuequasar:./src/Changelog.vue".parse().unwrap()],
+            sync_files: vec!["Yaml:./chart.yaml:appVersion".parse().unwrap()],
+            branch_for_release: Some(vec!["major".to_string(), "minor".to_string()]),
+            preset: Some(Preset::Rust),
+            yes: true,
+            no_detect: false,
+        })
+        .unwrap();
+        assert_eq!(config.version, "1.2.3");
+        assert!(config.branch_for_release);
+        assert_eq!(config.commands_that_release, vec!["major", "minor"]);
+        assert_eq!(
+            config.changelog_output_selections[0].output_filepath,
+            "./src/Changelog.vue"
+        );
+        let sync_files = config.version_sync_files.unwrap();
+        assert_eq!(sync_files[0].file_path, "./Cargo.toml");
+        assert_eq!(sync_files[1].version_key, "appVersion");
+    }
+
+    #[test]
+    fn test_build_config_rejects_invalid_answers() {
+        let invalid_version = InitOptions {
+            version: Some("one".to_string()),
+            yes: true,
+            ..Default::default()
+        };
+        assert!(build_config(&invalid_version).is_err());
+
+        let missing_major = InitOptions {
+            branch_for_release: Some(vec!["minor".to_string()]),
+            yes: true,
+            ..Default::default()
+        };
+        assert!(build_config(&missing_major).is_err());
+    }
+
+    #[test]
+    fn test_python_preset_follows_the_pyproject_layout() {
+        assert!(matches!(
+            python_sync_format(Some("[tool.poetry]\nname = \"app\"\nversion = \"1.0.0\"\n")),
+            SupportedSyncFileFormat::PoetryToml
+        ));
+        assert!(matches!(
+            python_sync_format(Some("[project]\nname = \"app\"\nversion = \"1.0.0\"\n")),
+            SupportedSyncFileFormat::PyprojectToml
+        ));
+        assert!(matches!(
+            python_sync_format(None),
+            SupportedSyncFileFormat::PyprojectToml
+        ));
+    }
+}
@@ -1,289 +1,794 @@
-mod change;
+//! GitScribe generates changelogs from conventional commits and keeps the project version in sync.
+//!
+//! Besides the `gitscribe` command line tool, the crate can be embedded in release tooling or `build.rs` scripts.
+//! Nothing outside the `handle_*` functions prints, prompts or exits, every failure is returned as a `GitScribeError`.
+//!
+//! ```no_run
+//! use gitscribe::{
+//!     get_changes, load_config, render_changelog, sync_version_to_file, TemplateOption,
+//!     VersionDesignation,
+//! };
+//!
+//! let config = load_config()?;
+//! let next = gitscribe::increment_version(&config, &VersionDesignation::Minor)?;
+//! let changes = get_changes(&config, &next)?;
+//! let markdown = render_changelog(&TemplateOption::Markdown, &next, &changes)?;
+//! if let Some(sync_files) = &config.version_sync_files {
+//!     sync_version_to_file(sync_files, &next.to_string())?;
+//! }
+//! # Ok::<(), gitscribe::GitScribeError>(())
+//! ```
+mod cargo_workspace;
+pub mod cascade;
+pub mod change;
 pub mod changelog;
 pub mod config;
+pub mod detect;
+mod edit;
+pub mod error;
+mod git;
+pub mod hooks;
+pub mod init;
+mod node_workspace;
+pub mod release_branch;
+pub mod transaction;
 pub mod util;
 pub mod version;
-mod version_file_sync;
+pub mod version_file_sync;
 
-use inquire::{
-    list_option::ListOption,
-    validator::{Validation, ValueRequiredValidator},
-    Text,
+pub use cascade::{dependency_graph, plan_cascade, CascadeStep};
+pub use change::{get_changes, get_group_changes, get_package_changes, Change, ChangeType};
+pub use changelog::{
+    render_cascaded_changelogs, render_changelog, render_changelogs, render_group_changelogs,
+    render_package_changelogs, ChangeTypeMap, TemplateOption,
+};
+pub use config::{
+    load_config, migrate_config, parse_config, BranchPolicy, Config, GroupConfig, PackageConfig,
+    CONFIG_VERSION,
+};
+pub use error::{GitScribeError, Result};
+pub use hooks::{HookStage, ReleaseHooks};
+pub use init::{handle_init, InitOptions, Preset};
+pub use release_branch::{release_lines, ReleaseBranch, ReleaseLine};
+pub use version::{increment_version, Version, VersionDesignation};
+pub use version_file_sync::{
+    check_version_sync, plan_version_sync, sync_version_to_file, SupportedSyncFileFormat,
+    SyncFileCheck, VersionSyncFile,
 };
-use std::{fs::OpenOptions, io::Write};
 
-use change::commit_changes;
-use changelog::generate_and_insert_changelogs;
+use cascade::plan_requirement_updates;
+use change::{commit_changes, push_release, reset_index, tag_release, undo_release};
 use colored::Colorize;
-use config::Config;
-use util::check_for_uncommitted_changes;
-use version::{increment_version, write_new_version_to_file, VersionDesignation};
-use version_file_sync::sync_version_to_file;
-
-use crate::{
-    changelog::TemplateOption,
-    version_file_sync::{SupportedSyncFileFormat, VersionSyncFile},
-};
+use config::ChangelogOutputOption;
+use git::{changed_paths, current_branch, head_commit, modified_paths};
+use hooks::{run_hook, HookContext};
+use indexmap::IndexMap;
+use release_branch::{check_release_branch, create_release_branch, is_release_branch};
+use std::path::Path;
+use transaction::ReleaseTransaction;
+use util::{check_branch_policy, check_for_uncommitted_changes};
+use version_file_sync::extend_version_sync_plan;
 
 pub const EXPECTED_CONFIG_FILE_NAME: &str = "gitscribe.json";
 
+/// The version sync files a release plans to write, by path, and its rendered changelogs
+type PlannedRelease = (
+    IndexMap<String, String>,
+    Vec<(ChangelogOutputOption, String)>,
+);
+
+/// Options for the release subcommands given on the command line
+/// # Fields
+/// * `push` - push the release to the configured remote, even if `push` is off in the config
+#[derive(Debug, Clone, Default)]
+pub struct ReleaseOptions {
+    pub push: bool,
+}
+
 /// Handles the version change when the any subcommand is used
+///
+/// The release is staged: the new config, version sync files and changelogs are all computed in memory
+/// and validated first, then written atomically. If a write, the release commit, the tag or the release branch fails,
+/// every touched file is restored and the release commit undone so the repo is never left half-bumped.
+/// The configured hooks run at each stage, see `ReleaseHooks`, and a failing hook undoes the whole release.
 /// # Arguments
 /// * `config` - The config struct
 /// * `version_designation` - The version designation
+/// * `options` - The options given on the command line
+/// # Errors
+/// * Any error from the release steps, after the touched files have been restored
+/// * A hook error if a hook fails, after the release has been undone
+/// * A git error if the push fails, the release is then only committed locally
 /// # Examples
-/// ```
-/// use gitscribe::Config;
+/// ```no_run
+/// use gitscribe::{config::Config, version::VersionDesignation, ReleaseOptions};
 /// let config = Config::create_default();
-/// gitscribe::handle_version_bump(config, VersionDesignation::Patch);
+/// gitscribe::handle_version_bump(config, VersionDesignation::Patch, &ReleaseOptions::default())
+///     .unwrap();
 /// ```
-pub fn handle_version_bump(config: Config, version_designation: VersionDesignation) {
+pub fn handle_version_bump(
+    config: Config,
+    version_designation: VersionDesignation,
+    options: &ReleaseOptions,
+) -> Result<()> {
     // check if there are uncommitted changes
-    check_for_uncommitted_changes();
-    let version = increment_version(&config, &version_designation);
-    let config = write_new_version_to_file(config.clone(), version.clone());
-
-    match config.version_sync_files.as_ref() {
-        Some(sync_files) => {
-            let file_sync_result = sync_version_to_file(sync_files.clone(), version.to_string());
-            match file_sync_result {
-                Ok(_) => {
-                    println!("{}", "All Version Files Updated".green());
-                }
-                Err(_) => {
-                    println!("{}", "Failed to update version in files".red());
-                }
-            }
-        }
-        None => {}
-    }
-
-    let changelog = generate_and_insert_changelogs(&version, &config);
-
-    match changelog {
-        Ok(changelog) => {
-            changelog.iter().for_each(|log| println!("{}", log.cyan()));
-        }
-        Err(_) => {
-            println!("{}", "Failed to generate changelog".red());
-        }
+    check_for_uncommitted_changes()?;
+    check_branch_policy(config.branch_policy.as_ref(), &version_designation)?;
+    let branch = current_branch()?;
+    let push = options.push || config.push;
+    if push && branch.is_none() {
+        return Err(GitScribeError::Git(
+            "HEAD is detached, check out a branch to push the release".to_string(),
+        ));
     }
+    check_release_branch(&config, branch.as_deref(), &version_designation)?;
+    let version = increment_version(&config, &version_designation)?;
+    println!(
+        "{} {}.{}.{}",
+        "New version:".underline(),
+        version.major.to_string().green(),
+        version.minor.to_string().green(),
+        version.patch.to_string().green()
+    );
+    let message = config.release_commit_message(&version.to_string(), &config.version, "")?;
+    let context = HookContext {
+        version: version.to_string(),
+        previous_version: config.version.clone(),
+        ..HookContext::default()
+    };
+    let mut config = config;
+    config.version = version.to_string();
 
-    commit_changes(version.to_string());
+    let mut release = Release::new(&config, context);
+    release.hook(HookStage::PreBump)?;
+    let (planned, changelogs) =
+        plan_release(&config, &version).map_err(|err| release.abort(err))?;
+    release.write(planned, changelogs)?;
+    release.commit(&message)?;
     println!(
-        "{} \n {}",
-        "✅New version has been committed, and changelog has been updated.".green(),
-        "🚀Don't forget to push your changes!".cyan()
+        "{}",
+        "✅New version has been committed, and changelog has been updated.".green()
     );
+    let mut refs = branch
+        .iter()
+        .map(|branch| format!("refs/heads/{branch}"))
+        .collect::<Vec<String>>();
 
+    // a release made on a release branch stays on its line, and is tagged so the changelogs
+    // of other branches pick it up under its version
+    let on_release_branch = branch
+        .as_deref()
+        .is_some_and(|branch| is_release_branch(&config.release_branch_pattern(), branch));
+    if on_release_branch {
+        let tag = format!("{}{}", config.tag_prefix(), version);
+        release.tag(&tag)?;
+        println!("{}", format!("✅Hotfix {tag} has been tagged.").green());
+        refs.push(format!("refs/tags/{tag}"));
+    }
     if config.branch_for_release
+        && !on_release_branch
         && config
             .commands_that_release
             .contains(&version_designation.to_string())
     {
-        branch_for_release(&config);
+        let release_branch =
+            branch_for_release(&config, &version).map_err(|err| release.abort(err))?;
+        if let Some(release_branch) = release_branch {
+            release.branches.push(release_branch.clone());
+            refs.push(format!("refs/heads/{release_branch}"));
+        }
     }
+    release.hook(HookStage::PostTag)?;
+    publish_release(&config, push, &refs)
 }
 
-/// Handles the initialization of the config file
-pub fn handle_init() {
-    let config_file = std::fs::read_to_string(EXPECTED_CONFIG_FILE_NAME);
-    match config_file {
-        Ok(_) => {
-            println!(
-                "🤬Config file already exists: {}. Please remove it if you want to reinitialize.",
-                EXPECTED_CONFIG_FILE_NAME.red()
-            );
-            std::process::exit(1);
-        }
-        Err(_) => {}
+/// Handles the version change of a single monorepo package when any subcommand is used with `--package`
+///
+/// Only the package's version, sync files and changelogs are updated, from the commits the package owns.
+/// The release commit is tagged with the package's tag prefix e.g. `api@1.2.0`
+///
+/// If the package is a member of a release group, or the name is a group, every member is bumped to
+/// one version past the highest member version and the group's combined changelogs are rendered too.
+/// Packages depending on the released ones are released along with them with at least a patch bump,
+/// the plan is printed before anything is written
+/// # Arguments
+/// * `config` - The config struct
+/// * `package_name` - The name of the package or release group to release
+/// * `version_designation` - The version designation
+/// * `options` - The options given on the command line
+/// # Errors
+/// * A config error if there is no package or group with the name
+/// * Any error from the release steps, after the touched files have been restored
+/// * A git error if the push fails, the release is then only committed locally
+/// # Examples
+/// ```no_run
+/// use gitscribe::{version::VersionDesignation, ReleaseOptions};
+/// let config = gitscribe::load_config().unwrap();
+/// let options = ReleaseOptions::default();
+/// gitscribe::handle_package_version_bump(config, "api", VersionDesignation::Patch, &options)
+///     .unwrap();
+/// ```
+pub fn handle_package_version_bump(
+    config: Config,
+    package_name: &str,
+    version_designation: VersionDesignation,
+    options: &ReleaseOptions,
+) -> Result<()> {
+    check_for_uncommitted_changes()?;
+    check_branch_policy(config.branch_policy.as_ref(), &version_designation)?;
+    let branch = current_branch()?;
+    let push = options.push || config.push;
+    if push && branch.is_none() {
+        return Err(GitScribeError::Git(
+            "HEAD is detached, check out a branch to push the release".to_string(),
+        ));
     }
+    check_release_branch(&config, branch.as_deref(), &version_designation)?;
+    let (released, version) = match config.group_for(package_name) {
+        Some(group) => (
+            group.members.clone(),
+            config.group_version(group)?.bump(&version_designation),
+        ),
+        None => {
+            let package = config.package(package_name)?;
+            (
+                vec![package.name.clone()],
+                Version::new(package.version.clone())?.bump(&version_designation),
+            )
+        }
+    };
+    println!(
+        "{} {} {}",
+        "New version:".underline(),
+        package_name.cyan(),
+        version.to_string().green()
+    );
+    let steps = plan_cascade(&config, &released, &version)?;
+    println!("{}", "Release plan:".underline());
+    steps
+        .iter()
+        .for_each(|step| println!("{}", format!("  {step}").cyan()));
 
-    let mut config = Config::create_default();
-    // walk through the config file creation
-    config.version = Text::new("Enter the initial version")
-        .with_help_message("The initial version of the application")
-        .with_default(config.version.clone().as_str())
-        .prompt()
-        .unwrap();
-    // handle the project repo, defaults to None
-    let project_repo = Text::new("Enter the project repository")
-        .with_help_message("eg. https://github.com/mikaelmello/inquire/")
-        .with_default(
-            config
-                .project_repo
-                .clone()
-                .unwrap_or("".to_string())
-                .as_str(),
-        )
-        .prompt()
-        .unwrap();
-    if project_repo.len() > 0 {
-        config.project_repo = Some(project_repo);
+    let mut config = config;
+    for configured in config.packages.iter_mut() {
+        if let Some(step) = steps.iter().find(|step| step.package == configured.name) {
+            configured.version = step.version.to_string();
+        }
     }
-    config.branch_for_release = inquire::Confirm::new("Create a branch for releases?")
-        .with_help_message("'y' for yes or 'n' for no")
-        .prompt()
-        .unwrap();
-    if config.branch_for_release {
-        config.commands_that_release = inquire::MultiSelect::new(
-            "Select Commands That Create A Release",
-            vec![
-                "major".to_string(),
-                "minor".to_string(),
-                "patch".to_string(),
-            ],
-        )
-        .with_default(&[0, 1]) //default to major and minor
-        .with_validator(|input: &[ListOption<&String>]| {
-            // ensure major is selected
-            if input.iter().any(|option| option.value == "major") {
-                Ok(Validation::Valid)
-            } else {
-                Ok(Validation::Invalid("Major must be selected".into()))
-            }
-        })
-        .prompt()
-        .unwrap();
+
+    let mut tags = vec![];
+    let mut previous_tags = vec![];
+    for step in &steps {
+        let tag_prefix = config.package(&step.package)?.tag_prefix();
+        tags.push(format!("{tag_prefix}{}", step.version));
+        previous_tags.push(format!("{tag_prefix}{}", step.previous));
     }
+    let context = HookContext {
+        version: tags.join(", "),
+        previous_version: previous_tags.join(", "),
+        package: steps
+            .iter()
+            .map(|step| step.package.as_str())
+            .collect::<Vec<&str>>()
+            .join(", "),
+        notes_file: None,
+    };
+    let message = config.release_commit_message(
+        &context.version,
+        &context.previous_version,
+        &context.package,
+    )?;
 
-    // handle the changelog output selections
-    let mut changelog_output_selections = vec![];
+    let mut release = Release::new(&config, context);
+    release.hook(HookStage::PreBump)?;
+    let (planned, changelogs) =
+        plan_package_release(&config, &steps).map_err(|err| release.abort(err))?;
+    release.write(planned, changelogs)?;
+    release.commit(&message)?;
+    for tag in &tags {
+        release.tag(tag)?;
+    }
     println!(
         "{}",
-        "----------------- Output Files -----------------".green()
+        format!(
+            "✅{} committed and tagged, and the changelogs have been updated.",
+            tags.join(", ")
+        )
+        .green()
     );
+    let refs = branch
+        .iter()
+        .map(|branch| format!("refs/heads/{branch}"))
+        .chain(tags.iter().map(|tag| format!("refs/tags/{tag}")))
+        .collect::<Vec<String>>();
+    release.hook(HookStage::PostTag)?;
+    publish_release(&config, push, &refs)
+}
+
+/// Pushes the release refs to the configured remote, or prints the command to push them
+/// # Arguments
+/// * `config` - The config struct
+/// * `push` - Whether to push, from `--push` or the config
+/// * `refs` - The full names of the refs the release created or moved
+/// # Errors
+/// * A git error if the push fails, nothing is pushed then as the push is atomic
+fn publish_release(config: &Config, push: bool, refs: &[String]) -> Result<()> {
+    let remote = config.push_remote();
+    let short_refs = refs
+        .iter()
+        .map(|refname| {
+            refname
+                .trim_start_matches("refs/heads/")
+                .trim_start_matches("refs/tags/")
+        })
+        .collect::<Vec<&str>>()
+        .join(" ");
+    if !push {
+        println!(
+            "{}",
+            format!("🚀Don't forget to push your changes: git push --atomic {remote} {short_refs}")
+                .cyan()
+        );
+        return Ok(());
+    }
+    push_release(&remote, refs).map_err(|err| {
+        GitScribeError::Git(format!(
+            "Failed to push {short_refs} to {remote}, the release is committed locally only: {err}"
+        ))
+    })?;
+    println!("{}", format!("🚀Pushed {short_refs} to {remote}.").green());
+    Ok(())
+}
+
+/// A release being written, it runs the release hooks at each stage and undoes the release if a step fails
+struct Release<'a> {
+    config: &'a Config,
+    context: HookContext,
+    transaction: ReleaseTransaction,
+    /// The commit HEAD pointed to before the release
+    head: Option<String>,
+    /// The files of the release commit, once committed
+    committed: Vec<String>,
+    tags: Vec<String>,
+    branches: Vec<String>,
+}
+
+impl<'a> Release<'a> {
+    fn new(config: &'a Config, context: HookContext) -> Self {
+        Release {
+            config,
+            context,
+            transaction: ReleaseTransaction::new(),
+            head: head_commit(),
+            committed: vec![],
+            tags: vec![],
+            branches: vec![],
+        }
+    }
 
-    loop {
-        let template_option =
-            inquire::Select::new("Select A Changelog Template", TemplateOption::values())
-                .prompt()
-                .unwrap();
+    /// Returns true if any release hook is configured, the hooks may then change any file
+    fn has_hooks(&self) -> bool {
+        self.config
+            .hooks
+            .as_ref()
+            .is_some_and(|hooks| !hooks.is_empty())
+    }
 
-        let default_filepath = match template_option {
-            TemplateOption::Markdown => "./CHANGELOG.md",
-            TemplateOption::VueQuasar => "./src/components/GitscribeChangelog.vue",
+    /// Runs the hook commands of a stage, undoing the release if one fails
+    /// # Errors
+    /// * A hook error if a command fails, after the release has been undone
+    fn hook(&mut self, stage: HookStage) -> Result<()> {
+        let Some(hooks) = &self.config.hooks else {
+            return Ok(());
+        };
+        let commands = hooks.commands(stage);
+        if commands.is_empty() {
+            return Ok(());
+        }
+        let changed_files = match self.committed.is_empty() {
+            true => changed_paths().map_err(|err| self.abort(err))?,
+            false => self.committed.clone(),
         };
-        let output_filepath = Text::new("Enter the output filepath")
-            .with_help_message("The output filepath relative to the root of the project")
-            .with_validator(ValueRequiredValidator::new(
-                "The output filepath cannot be empty",
-            ))
-            .with_default(default_filepath)
-            .prompt()
-            .unwrap();
-
-        changelog_output_selections.push(config::ChangelogOutputOption {
-            template_option,
-            output_filepath,
-        });
-        let add_another = inquire::Confirm::new("Add another changelog file output?")
-            .with_help_message("'y' for yes or 'n' for no")
-            .prompt()
-            .unwrap();
-        if !add_another {
-            config.changelog_output_selections = changelog_output_selections;
-            break;
+        for command in commands {
+            println!("{}", format!("Running {stage} hook: {command}").cyan());
+            run_hook(command, stage, &self.context, &changed_files)
+                .map_err(|err| self.abort(err))?;
         }
+        Ok(())
     }
-    let add_version_sync_files = inquire::Confirm::new("Add a file to sync the version with?")
-        .with_help_message("'y' for yes or 'n' for no")
-        .prompt()
-        .unwrap();
-    if add_version_sync_files {
-        println!(
-            "{}",
-            "----------------- Files To Sync -----------------".green()
-        );
-        // handle the version sync files
-        let mut version_sync_files = vec![];
-        loop {
-            let file_path = Text::new("Enter the file path")
-                .with_help_message("The file path relative to the root of the project")
-                .with_validator(ValueRequiredValidator::new("The file path cannot be empty"))
-                .prompt()
-                .unwrap();
-            let file_format =
-                inquire::Select::new("Select A File Format", SupportedSyncFileFormat::values())
-                    .prompt()
-                    .unwrap();
-            let version_key = Text::new("Enter the version key")
-                .with_help_message(
-                    "The Key in the file to update with the new version. e.g. version",
-                )
-                .with_default("version")
-                .prompt()
-                .unwrap();
-
-            version_sync_files.push(VersionSyncFile {
-                file_path,
-                file_format,
-                version_key,
-            });
-            let add_another = inquire::Confirm::new("Add another version sync file?").with_help_message(
-            "If you want to add another version sync file, select yes. Otherwise, select no.",
-        )
-            .prompt()
-            .unwrap();
-            if !add_another {
-                config.version_sync_files = Some(version_sync_files);
-                break;
+
+    /// Writes the version files and then the changelogs, running the hooks after each
+    /// # Arguments
+    /// * `planned` - The planned version sync files
+    /// * `changelogs` - The rendered changelogs
+    fn write(
+        &mut self,
+        planned: IndexMap<String, String>,
+        changelogs: Vec<(ChangelogOutputOption, String)>,
+    ) -> Result<()> {
+        self.context.notes_file = changelogs
+            .first()
+            .map(|(output_selection, _)| output_selection.output_filepath.clone());
+        let release_summary = stage_version_files(&mut self.transaction, self.config, planned)
+            .map_err(|err| self.abort(err))?;
+        self.transaction
+            .apply()
+            .map_err(|err| self.abort(err.into()))?;
+        release_summary.iter().for_each(|line| println!("{}", line));
+        self.hook(HookStage::PostSync)?;
+
+        let release_summary = stage_changelogs(&mut self.transaction, changelogs);
+        self.transaction
+            .apply()
+            .map_err(|err| self.abort(err.into()))?;
+        release_summary.iter().for_each(|line| println!("{}", line));
+        self.hook(HookStage::PostChangelog)
+    }
+
+    /// Commits the written release, with any tracked file the hooks changed
+    ///
+    /// Files the hooks created are left out of the commit, they are not tracked and may be build output
+    /// # Arguments
+    /// * `message` - The release commit message
+    fn commit(&mut self, message: &str) -> Result<()> {
+        self.hook(HookStage::PreCommit)?;
+        let mut paths = self
+            .transaction
+            .staged_paths()
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+        if self.has_hooks() {
+            let modified = modified_paths().map_err(|err| self.abort(err))?;
+            for path in changed_paths().map_err(|err| self.abort(err))? {
+                if paths.contains(&path) {
+                    continue;
+                }
+                match modified.contains(&path) {
+                    true => paths.push(path),
+                    false => println!(
+                        "{}",
+                        format!(
+                            "{path} is not tracked, it has been left out of the release commit."
+                        )
+                        .yellow()
+                    ),
+                }
             }
         }
+        let commit = commit_changes(
+            self.config,
+            message,
+            &paths.iter().map(Path::new).collect::<Vec<&Path>>(),
+        );
+        match commit {
+            Ok(output) => println!("{}", output),
+            Err(err) => return Err(self.abort(err)),
+        }
+        self.committed = paths;
+        self.hook(HookStage::PostCommit)
+    }
+
+    /// Tags the release commit, undoing the release if the tag cannot be created
+    fn tag(&mut self, tag: &str) -> Result<()> {
+        tag_release(tag).map_err(|err| self.abort(err))?;
+        self.tags.push(tag.to_string());
+        Ok(())
     }
 
-    // create the file
-    let config_file = serde_json::to_string_pretty(&config).unwrap();
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create_new(true)
-        .open(EXPECTED_CONFIG_FILE_NAME)
-        .unwrap();
-    file.write_all(config_file.as_bytes()).unwrap();
-    println!("{}", "🚀Config file has been initialized.".green());
+    /// Undoes the release after a step failed, restoring every file in the transaction
+    ///
+    /// The release commit, tags and branches and any file the hooks changed are undone too,
+    /// as the release started from a clean working tree
+    /// # Returns
+    /// * The error of the failed step
+    fn abort(&mut self, err: GitScribeError) -> GitScribeError {
+        let restored = match &self.head {
+            Some(head) => self
+                .transaction
+                .rollback()
+                .map_err(GitScribeError::from)
+                .and_then(|_| undo_release(head, &self.tags, &self.branches)),
+            None => reset_index().and_then(|_| Ok(self.transaction.rollback()?)),
+        };
+        match restored {
+            Ok(_) => println!("{}", "All release files have been restored.".yellow()),
+            Err(restore_err) => println!(
+                "🤬Failed to restore release files: {}",
+                restore_err.to_string().red()
+            ),
+        }
+        err
+    }
 }
 
-fn branch_for_release(config: &Config) {
-    let mut branch_version = config.version.clone();
-    // replace the last part of the version with an X
-    if !config.commands_that_release.contains(&"patch".to_string()) {
-        branch_version = branch_version
-            .split(".")
-            .take(2)
-            .collect::<Vec<&str>>()
-            .join(".")
-            + ".X";
+/// Handles the check subcommand, comparing every version sync file against the configured version
+///
+/// Nothing is written, so it can run in CI or as a pre-commit hook
+/// # Arguments
+/// * `config` - The config struct
+/// # Errors
+/// * A `VersionDrift` error listing every key that holds another version or could not be read
+/// # Examples
+/// ```no_run
+/// let config = gitscribe::load_config().unwrap();
+/// gitscribe::handle_check(&config).unwrap();
+/// ```
+pub fn handle_check(config: &Config) -> Result<()> {
+    // the root version and every package version, with the files that should hold it
+    let mut versions = vec![(
+        None,
+        config.version.clone(),
+        config.version_sync_files.clone().unwrap_or_default(),
+    )];
+    for package in &config.packages {
+        versions.push((
+            Some(package.name.clone()),
+            package.version.clone(),
+            package.version_sync_files.clone(),
+        ));
     }
-    // replace the second to last part of the version with an X
-    if !config.commands_that_release.contains(&"minor".to_string()) {
-        branch_version = branch_version
-            .split(".")
-            .enumerate()
-            .map(|(i, part)| if i == 1 { "X" } else { part })
-            .collect::<Vec<&str>>()
-            .join(".");
+    versions.retain(|(_, _, version_files)| !version_files.is_empty());
+    if versions.is_empty() {
+        println!("{}", "No version sync files are configured.".yellow());
+        return Ok(());
+    }
+
+    let mut drifted = vec![];
+    for (package, version, version_files) in versions {
+        match package {
+            Some(package) => println!(
+                "{} {} {}",
+                "Configured version:".underline(),
+                package.cyan(),
+                version.green()
+            ),
+            None => println!("{} {}", "Configured version:".underline(), version.green()),
+        }
+        for check in check_version_sync(&version_files) {
+            let label = format!(
+                "{} ({} {})",
+                check.file_path, check.file_format, check.version_key
+            );
+            match &check.found {
+                Ok(found) if check.is_in_sync(&version) => {
+                    println!("✅{} = {}", label, found.green())
+                }
+                Ok(found) => println!("🤬{} = {}", label, found.red()),
+                Err(err) => println!("🤬{} {}", label, err.to_string().red()),
+            }
+            if !check.is_in_sync(&version) {
+                drifted.push(format!("{} ({})", check.file_path, check.version_key));
+            }
+        }
     }
 
-    let output = std::process::Command::new("git")
-        .args(&[
-            "checkout",
-            "-b",
-            format!("release/{}", branch_version).as_str(),
-        ])
-        .output()
-        .expect("Failed to execute command");
-    if output.status.success() {
-        println!("{}", "✅Branch has been created for the release.".green());
-        println!("{}", "  ℹ️ To publish your release run:".green());
+    if drifted.is_empty() {
         println!(
             "{}",
-            format!("      git push origin release/{branch_version}").cyan()
+            "✅All version files match the configured version.".green()
         );
+        Ok(())
     } else {
+        Err(GitScribeError::VersionDrift(drifted))
+    }
+}
+
+/// Handles the branches subcommand, listing the active release lines
+/// # Arguments
+/// * `config` - The config struct
+/// # Errors
+/// * A git error if the branches cannot be listed
+/// # Examples
+/// ```no_run
+/// let config = gitscribe::load_config().unwrap();
+/// gitscribe::handle_release_lines(&config).unwrap();
+/// ```
+pub fn handle_release_lines(config: &Config) -> Result<()> {
+    let lines = release_lines(config)?;
+    if lines.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "No release branches match {}.",
+                config.release_branch_pattern()
+            )
+            .yellow()
+        );
+        return Ok(());
+    }
+    println!("{}", "Release lines:".underline());
+    for line in lines {
+        match line.version {
+            Some(version) => println!("  {} {}", line.branch.cyan(), version.green()),
+            None => println!(
+                "  {} {}",
+                line.branch.cyan(),
+                format!("(no {EXPECTED_CONFIG_FILE_NAME})").yellow()
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Handles the config migrate subcommand, upgrading the config file in place to the current config version
+/// # Errors
+/// * A config error if the config file cannot be read or parsed, or was written for a newer gitscribe
+/// * An io error if the config file cannot be written
+/// # Examples
+/// ```no_run
+/// gitscribe::handle_config_migrate().unwrap();
+/// ```
+pub fn handle_config_migrate() -> Result<()> {
+    let contents = std::fs::read_to_string(EXPECTED_CONFIG_FILE_NAME).map_err(|_| {
+        GitScribeError::Config(format!(
+            "Failed to read config file: {EXPECTED_CONFIG_FILE_NAME}. Please run `gitscribe init` to create a new config file."
+        ))
+    })?;
+    let (config, from, dropped) = migrate_config(&contents)?;
+    let migrated = serde_json::to_string_pretty(&config)
+        .map_err(|err| GitScribeError::Config(format!("Failed to serialize config: {err}")))?;
+    if migrated == contents.trim_end() {
+        println!(
+            "{}",
+            format!("✅{EXPECTED_CONFIG_FILE_NAME} is already at config version {CONFIG_VERSION}.")
+                .green()
+        );
+        return Ok(());
+    }
+    transaction::write_atomic(Path::new(EXPECTED_CONFIG_FILE_NAME), migrated.as_bytes())?;
+    for key in &dropped {
         println!(
             "{}",
-            format!("🤬Failed to create a branch for the release/{branch_version}.").red()
+            format!("{key} is not a config field and was ignored, it has been removed.").yellow()
+        );
+    }
+    println!(
+        "{}",
+        format!(
+            "✅Migrated {EXPECTED_CONFIG_FILE_NAME} from config version {from} to {CONFIG_VERSION}."
+        )
+        .green()
+    );
+    Ok(())
+}
+
+/// Computes every file the release of the root version writes
+/// # Arguments
+/// * `config` - The config struct, already holding the new version
+/// * `version` - The new version
+/// # Returns
+/// * The planned version sync files and the rendered changelogs
+fn plan_release(config: &Config, version: &Version) -> Result<PlannedRelease> {
+    let planned = plan_version_sync(
+        &config.version_sync_files.clone().unwrap_or_default(),
+        &version.to_string(),
+    )?;
+    let changelogs = render_changelogs(version, config)?;
+    Ok((planned, changelogs))
+}
+
+/// Computes every file the release of monorepo packages writes
+/// # Arguments
+/// * `config` - The config struct, already holding the new versions
+/// * `steps` - The packages to release, as planned by `plan_cascade`
+/// # Returns
+/// * The planned version sync files and the rendered changelogs
+fn plan_package_release(config: &Config, steps: &[CascadeStep]) -> Result<PlannedRelease> {
+    // planned together, so packages sharing a file like Cargo.lock all end up in it
+    let mut planned = IndexMap::new();
+    for step in steps {
+        let package = config.package(&step.package)?;
+        extend_version_sync_plan(
+            &mut planned,
+            &package.version_sync_files,
+            &step.version.to_string(),
+        )?;
+    }
+    plan_requirement_updates(&mut planned, config, steps)?;
+
+    let mut changelogs = vec![];
+    for step in steps {
+        let updated_dependencies = step
+            .updated_dependencies
+            .iter()
+            .filter_map(|dependency| {
+                steps
+                    .iter()
+                    .find(|step| &step.package == dependency)
+                    .map(|released| (dependency.clone(), released.version.clone()))
+            })
+            .collect::<Vec<(String, Version)>>();
+        let package = config.package(&step.package)?;
+        changelogs.extend(render_cascaded_changelogs(
+            &step.version,
+            config,
+            package,
+            &updated_dependencies,
+        )?);
+    }
+    // a group is only ever released whole, so any released member holds the group version
+    for group in &config.groups {
+        let Some(step) = steps
+            .iter()
+            .find(|step| group.members.contains(&step.package))
+        else {
+            continue;
+        };
+        let members = config.group_members(group)?;
+        changelogs.extend(render_group_changelogs(
+            &step.version,
+            config,
+            group,
+            &members,
+        )?);
+    }
+    Ok((planned, changelogs))
+}
+
+/// Stages the config file and the planned version sync files
+/// # Returns
+/// * The lines summarizing the staged files
+fn stage_version_files(
+    transaction: &mut ReleaseTransaction,
+    config: &Config,
+    planned: IndexMap<String, String>,
+) -> Result<Vec<String>> {
+    let mut release_summary = vec![];
+    let config_file = serde_json::to_string_pretty(config)
+        .map_err(|err| GitScribeError::Config(format!("Failed to serialize config: {err}")))?;
+    transaction.stage(EXPECTED_CONFIG_FILE_NAME, config_file);
+
+    if !planned.is_empty() {
+        for (file_path, contents) in planned {
+            release_summary.push(format!("✅Updated version in {}", file_path));
+            transaction.stage(file_path, contents);
+        }
+        release_summary.push("All Version Files Updated".green().to_string());
+    }
+    Ok(release_summary)
+}
+
+/// Stages the rendered changelogs
+/// # Returns
+/// * The lines summarizing the staged files
+fn stage_changelogs(
+    transaction: &mut ReleaseTransaction,
+    changelogs: Vec<(ChangelogOutputOption, String)>,
+) -> Vec<String> {
+    let mut release_summary = vec![];
+    for (output_selection, rendered_log) in changelogs {
+        transaction.stage(&output_selection.output_filepath, rendered_log);
+        release_summary.push(
+            format!(
+                " - Generated {} changelog at {}",
+                output_selection.template_option, output_selection.output_filepath
+            )
+            .cyan()
+            .to_string(),
         );
-        std::process::exit(1);
+    }
+    release_summary
+}
+
+/// Creates the release branch of the new version from the release commit, leaving the checkout alone
+/// # Returns
+/// * The name of the branch if it was created
+fn branch_for_release(config: &Config, version: &Version) -> Result<Option<String>> {
+    match create_release_branch(config, version)? {
+        ReleaseBranch::Created(branch) => {
+            println!(
+                "{}",
+                format!("✅Branch {branch} has been created for the release.").green()
+            );
+            Ok(Some(branch))
+        }
+        ReleaseBranch::Exists(branch) => {
+            println!(
+                "{}",
+                format!("Branch {branch} already exists, it has been left unchanged.").yellow()
+            );
+            Ok(None)
+        }
     }
 }
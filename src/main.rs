@@ -1,8 +1,16 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use colored::Colorize;
 
 use gitscribe::{
-    config::load_config, handle_init, handle_version_bump, util::print_banner,
+    config::{load_config, ChangelogOutputOption},
+    error::{GitScribeError, Result},
+    handle_check, handle_config_migrate, handle_init, handle_package_version_bump,
+    handle_release_lines, handle_version_bump,
+    init::{InitOptions, Preset},
+    util::{print_banner, print_uncommitted_changes},
     version::VersionDesignation,
+    version_file_sync::VersionSyncFile,
+    ReleaseOptions,
 };
 
 #[derive(Parser)]
@@ -15,49 +23,115 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Initializes a new GitScribe configuration file
-    Init,
+    Init(InitArgs),
     /// Bumps the version by a patch e.g. 1.0.0 -> 1.0.1
-    Patch,
+    Patch(ReleaseArgs),
     /// Bumps the version by a minor e.g. 1.0.4 -> 1.1.0
-    Minor,
+    Minor(ReleaseArgs),
     /// Bumps the version by a major e.g. 1.0.4 -> 2.0.0
-    Major,
+    Major(ReleaseArgs),
+    /// Checks every version sync file holds the configured version, exits with 9 if any do not
+    Check,
+    /// Lists the active release lines, the release branches and the version each is at
+    Branches,
+    /// Manages the gitscribe.json config file
+    #[command(subcommand)]
+    Config(ConfigCommands),
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    /// Upgrades gitscribe.json in place to the current config version
+    Migrate,
+}
+
+/// Options for the release subcommands
+#[derive(Args)]
+struct ReleaseArgs {
+    /// Release only this package of the monorepo, as named in gitscribe.json
+    #[arg(long, short)]
+    package: Option<String>,
+    /// Push the release commit, its tags and release branch to the configured remote in one atomic push
+    #[arg(long)]
+    push: bool,
+}
+
+/// Answers for the init prompts, anything not given is prompted for unless --yes is set
+#[derive(Args)]
+struct InitArgs {
+    /// The initial version e.g. 0.0.1
+    #[arg(long)]
+    version: Option<String>,
+    /// The project repository, used to link to commits
+    #[arg(long)]
+    repo: Option<String>,
+    /// A changelog output as TEMPLATE:PATH e.g. markdown:CHANGELOG.md, can be repeated
+    #[arg(long = "template", value_name = "TEMPLATE:PATH")]
+    templates: Vec<ChangelogOutputOption>,
+    /// A version sync file as FORMAT:PATH[:KEY[,KEY...]] e.g. CargoToml:./Cargo.toml:version, can be repeated
+    #[arg(long = "sync", value_name = "FORMAT:PATH[:KEY]")]
+    sync_files: Vec<VersionSyncFile>,
+    /// Create a release branch for these commands e.g. major,minor
+    #[arg(long, value_delimiter = ',', value_name = "COMMANDS")]
+    branch_for_release: Option<Vec<String>>,
+    /// Pre-fill the version sync files for a project type: rust, node or python
+    #[arg(long)]
+    preset: Option<Preset>,
+    /// Accept the default for every answer not given as a flag instead of prompting
+    #[arg(long, short)]
+    yes: bool,
+    /// Do not scan the project for manifests to offer as version sync files
+    #[arg(long)]
+    no_detect: bool,
+}
+
+impl From<InitArgs> for InitOptions {
+    fn from(args: InitArgs) -> Self {
+        InitOptions {
+            version: args.version,
+            repo: args.repo,
+            templates: args.templates,
+            sync_files: args.sync_files,
+            branch_for_release: args.branch_for_release,
+            preset: args.preset,
+            yes: args.yes,
+            no_detect: args.no_detect,
+        }
+    }
 }
 
 fn main() {
     print_banner();
     let cli = Cli::parse();
 
-    match cli.command {
-        Commands::Init => {
-            handle_init();
-        }
-        Commands::Patch {} => {
-            //load config file from gitscribe.json as str, if not there, create it
-            let config = load_config();
-            match config {
-                Some(_) => {}
-                None => std::process::exit(1),
-            }
-            handle_version_bump(config.unwrap(), VersionDesignation::Patch);
-        }
-        Commands::Minor {} => {
-            //load config file from gitscribe.json as str, if not there, create it
-            let config = load_config();
-            match config {
-                Some(_) => {}
-                None => std::process::exit(1),
-            }
-            handle_version_bump(config.unwrap(), VersionDesignation::Minor);
+    let result = match cli.command {
+        Commands::Init(args) => handle_init(args.into()),
+        Commands::Patch(args) => release(VersionDesignation::Patch, args),
+        Commands::Minor(args) => release(VersionDesignation::Minor, args),
+        Commands::Major(args) => release(VersionDesignation::Major, args),
+        Commands::Check => load_config().and_then(|config| handle_check(&config)),
+        Commands::Branches => load_config().and_then(|config| handle_release_lines(&config)),
+        Commands::Config(ConfigCommands::Migrate) => handle_config_migrate(),
+    };
+
+    // exit codes are documented on `GitScribeError::exit_code`
+    if let Err(err) = result {
+        match &err {
+            GitScribeError::UncommittedChanges(changes) => print_uncommitted_changes(changes),
+            err => println!("🤬{}", err.to_string().red()),
         }
-        Commands::Major {} => {
-            //load config file from gitscribe.json as str, if not there, create it
-            let config = load_config();
-            match config {
-                Some(_) => {}
-                None => std::process::exit(1),
-            }
-            handle_version_bump(config.unwrap(), VersionDesignation::Major);
+        std::process::exit(err.exit_code());
+    }
+}
+
+/// Loads the config file from gitscribe.json and bumps the version, or the version of a single package
+fn release(version_designation: VersionDesignation, args: ReleaseArgs) -> Result<()> {
+    let config = load_config()?;
+    let options = ReleaseOptions { push: args.push };
+    match args.package {
+        Some(package) => {
+            handle_package_version_bump(config, &package, version_designation, &options)
         }
+        None => handle_version_bump(config, version_designation, &options),
     }
 }
@@ -0,0 +1,586 @@
+//! Version sync for a Node package and its workspaces
+//!
+//! Starting from the root package.json, the version of the root and of every npm, yarn or pnpm
+//! workspace package is set, along with the ranges packages use to depend on each other.
+//! `package-lock.json`, `npm-shrinkwrap.json` and the specifiers in `pnpm-lock.yaml` are edited
+//! to match so the next install does not rewrite them. Yarn lockfiles do not record workspace
+//! versions, so they are left alone.
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::edit::{set_json_string, set_yaml_scalar, PathSegment};
+use crate::util::glob_directories;
+
+/// The package.json tables that reference other packages by range
+pub(crate) const DEPENDENCY_TABLES: [&str; 4] = [
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+/// The npm lockfiles that record the version of the root and workspace packages
+const NPM_LOCKFILES: [&str; 2] = ["package-lock.json", "npm-shrinkwrap.json"];
+
+/// A workspace package, `relative` is its directory relative to the root e.g. `packages/app`
+struct WorkspacePackage {
+    manifest_path: String,
+    relative: String,
+    contents: String,
+    version_set: bool,
+}
+
+/// Reads the version of the root package.json
+pub(crate) fn read_node_version(file: &str) -> Result<String, String> {
+    let json = serde_json::from_str::<serde_json::Value>(file).map_err(|err| err.to_string())?;
+    json.get("version")
+        .and_then(|version| version.as_str())
+        .map(|version| version.to_string())
+        .ok_or_else(|| "missing key 'version'".to_string())
+}
+
+/// Computes the new contents of the package.json files of the project and its lockfiles
+/// # Arguments
+/// * `manifest_path` - The path of the root package.json e.g. `./package.json`
+/// * `version` - The new version
+/// * `read` - Reads the current contents of a file, including contents already planned
+/// # Returns
+/// * The paths and new contents of the files that changed, or the reason the project could not be updated
+pub(crate) fn plan_node_sync(
+    manifest_path: &str,
+    version: &str,
+    read: &dyn Fn(&str) -> Result<String, String>,
+) -> Result<Vec<(String, String)>, String> {
+    let root_dir = Path::new(manifest_path)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let root = read(manifest_path)?;
+    let root_json = serde_json::from_str::<serde_json::Value>(&root)
+        .map_err(|err| format!("{manifest_path} is not valid JSON: {err}"))?;
+
+    // packages whose version is now the new version, so the ranges on them follow it
+    let mut bumped = HashSet::new();
+    if let Some(name) = root_json.get("name").and_then(|name| name.as_str()) {
+        bumped.insert(name.to_string());
+    }
+    let mut packages = vec![WorkspacePackage {
+        manifest_path: manifest_path.to_string(),
+        relative: String::new(),
+        contents: set_json_string(&root, &[key("version")], version)?,
+        version_set: true,
+    }];
+    for (manifest_path, relative) in workspace_packages(root_dir, &root_json, read)? {
+        let contents = read(&manifest_path)?;
+        let json = serde_json::from_str::<serde_json::Value>(&contents)
+            .map_err(|err| format!("{manifest_path} is not valid JSON: {err}"))?;
+        // private packages without a version are never published, leave them without one
+        let version_set = json
+            .get("version")
+            .is_some_and(|version| version.is_string());
+        let contents = match version_set {
+            true => {
+                if let Some(name) = json.get("name").and_then(|name| name.as_str()) {
+                    bumped.insert(name.to_string());
+                }
+                set_json_string(&contents, &[key("version")], version)?
+            }
+            false => contents,
+        };
+        packages.push(WorkspacePackage {
+            manifest_path,
+            relative,
+            contents,
+            version_set,
+        });
+    }
+
+    let mut planned = vec![];
+    for package in &mut packages {
+        package.contents = set_dependency_ranges(&package.contents, &[], &bumped, version)?;
+        planned.push((package.manifest_path.clone(), package.contents.clone()));
+    }
+
+    for lockfile in NPM_LOCKFILES {
+        let lock_path = root_dir.join(lockfile).to_string_lossy().to_string();
+        if !Path::new(&lock_path).is_file() {
+            continue;
+        }
+        let mut lock = read(&lock_path)?;
+        // a lockfile of a root package without a version holds none either
+        if read_node_version(&lock).is_ok() {
+            lock = set_json_string(&lock, &[key("version")], version)?;
+        }
+        let lock_json = serde_json::from_str::<serde_json::Value>(&lock)
+            .map_err(|err| format!("{lock_path} is not valid JSON: {err}"))?;
+        for package in &packages {
+            let Some(entry) = lock_json
+                .get("packages")
+                .and_then(|entries| entries.get(&package.relative))
+            else {
+                continue;
+            };
+            let entry_path = [key("packages"), key(&package.relative)];
+            if package.version_set && entry.get("version").is_some() {
+                let version_path = [key("packages"), key(&package.relative), key("version")];
+                lock = set_json_string(&lock, &version_path, version)?;
+            }
+            lock = set_dependency_ranges(&lock, &entry_path, &bumped, version)?;
+        }
+        planned.push((lock_path, lock));
+    }
+
+    let pnpm_lock_path = root_dir
+        .join("pnpm-lock.yaml")
+        .to_string_lossy()
+        .to_string();
+    if Path::new(&pnpm_lock_path).is_file() {
+        let lock = set_pnpm_specifiers(&read(&pnpm_lock_path)?, &bumped, version)?;
+        planned.push((pnpm_lock_path, lock));
+    }
+
+    Ok(planned)
+}
+
+/// Reads every version the project holds: the root and each workspace package with a version,
+/// and their entries in the npm lockfiles
+/// # Arguments
+/// * `manifest_path` - The path of the root package.json e.g. `./package.json`
+/// * `read` - Reads the current contents of a file
+/// # Returns
+/// * The path, the key and the version of each
+pub(crate) fn read_node_versions(
+    manifest_path: &str,
+    read: &dyn Fn(&str) -> Result<String, String>,
+) -> Result<Vec<(String, String, String)>, String> {
+    let root_dir = Path::new(manifest_path)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let root_json = serde_json::from_str::<serde_json::Value>(&read(manifest_path)?)
+        .map_err(|err| format!("{manifest_path} is not valid JSON: {err}"))?;
+    let mut versions = vec![];
+    let mut relatives = vec![];
+    let packages = std::iter::once((manifest_path.to_string(), String::new()))
+        .chain(workspace_packages(root_dir, &root_json, read)?);
+    for (package_path, relative) in packages {
+        let json = serde_json::from_str::<serde_json::Value>(&read(&package_path)?)
+            .map_err(|err| format!("{package_path} is not valid JSON: {err}"))?;
+        if let Some(version) = json.get("version").and_then(|version| version.as_str()) {
+            versions.push((package_path, "version".to_string(), version.to_string()));
+            relatives.push(relative);
+        }
+    }
+
+    for lockfile in NPM_LOCKFILES {
+        let lock_path = root_dir.join(lockfile).to_string_lossy().to_string();
+        if !Path::new(&lock_path).is_file() {
+            continue;
+        }
+        let lock = serde_json::from_str::<serde_json::Value>(&read(&lock_path)?)
+            .map_err(|err| format!("{lock_path} is not valid JSON: {err}"))?;
+        if let Some(version) = lock.get("version").and_then(|version| version.as_str()) {
+            versions.push((
+                lock_path.clone(),
+                "version".to_string(),
+                version.to_string(),
+            ));
+        }
+        for relative in &relatives {
+            let version = lock
+                .get("packages")
+                .and_then(|entries| entries.get(relative))
+                .and_then(|entry| entry.get("version"))
+                .and_then(|version| version.as_str());
+            if let Some(version) = version {
+                versions.push((
+                    lock_path.clone(),
+                    format!("packages.\"{relative}\".version"),
+                    version.to_string(),
+                ));
+            }
+        }
+    }
+    Ok(versions)
+}
+
+/// Finds the package.json of every workspace package, without the root package.json
+/// # Arguments
+/// * `manifest_path` - The path of the root package.json e.g. `./package.json`
+/// * `read` - Reads the current contents of a file
+pub(crate) fn workspace_members(
+    manifest_path: &str,
+    read: &dyn Fn(&str) -> Result<String, String>,
+) -> Result<Vec<String>, String> {
+    let root_dir = Path::new(manifest_path)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let root_json = serde_json::from_str::<serde_json::Value>(&read(manifest_path)?)
+        .map_err(|err| format!("{manifest_path} is not valid JSON: {err}"))?;
+    Ok(workspace_packages(root_dir, &root_json, read)?
+        .into_iter()
+        .map(|(manifest_path, _)| manifest_path)
+        .collect())
+}
+
+/// Finds the workspace packages of the root package.json and its pnpm-workspace.yaml
+/// # Returns
+/// * The manifest paths and the package directories relative to the root
+fn workspace_packages(
+    root_dir: &Path,
+    root_json: &serde_json::Value,
+    read: &dyn Fn(&str) -> Result<String, String>,
+) -> Result<Vec<(String, String)>, String> {
+    let pnpm_workspace = root_dir.join("pnpm-workspace.yaml");
+    let pnpm_patterns = match pnpm_workspace.is_file() {
+        true => pnpm_workspace_patterns(&read(&pnpm_workspace.to_string_lossy())?)?,
+        false => vec![],
+    };
+    let patterns = workspace_patterns(root_json)
+        .into_iter()
+        .chain(pnpm_patterns)
+        .collect::<Vec<String>>();
+    workspace_manifests(root_dir, &patterns)
+}
+
+fn key(key: &str) -> PathSegment {
+    PathSegment::Key(key.to_string())
+}
+
+/// The workspace globs of a package.json, either `"workspaces": [...]`
+/// or yarn's `"workspaces": { "packages": [...] }`
+fn workspace_patterns(json: &serde_json::Value) -> Vec<String> {
+    let workspaces = json.get("workspaces");
+    workspaces
+        .and_then(|workspaces| workspaces.get("packages"))
+        .or(workspaces)
+        .and_then(|patterns| patterns.as_array())
+        .map(|patterns| {
+            patterns
+                .iter()
+                .filter_map(|pattern| pattern.as_str())
+                .map(|pattern| pattern.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The `packages` globs of a pnpm-workspace.yaml
+fn pnpm_workspace_patterns(file: &str) -> Result<Vec<String>, String> {
+    let yaml = serde_yaml::from_str::<serde_yaml::Value>(file)
+        .map_err(|err| format!("pnpm-workspace.yaml is not valid YAML: {err}"))?;
+    Ok(yaml
+        .get("packages")
+        .and_then(|packages| packages.as_sequence())
+        .map(|patterns| {
+            patterns
+                .iter()
+                .filter_map(|pattern| pattern.as_str())
+                .map(|pattern| pattern.to_string())
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Finds the package.json of every workspace package, globs starting with `!` exclude packages
+/// # Returns
+/// * The manifest paths and the package directories relative to the root
+fn workspace_manifests(
+    root_dir: &Path,
+    patterns: &[String],
+) -> Result<Vec<(String, String)>, String> {
+    let expand = |pattern: &str| -> Result<Vec<String>, String> {
+        let directories = glob_directories(root_dir, pattern)
+            .map_err(|err| format!("invalid workspace '{pattern}': {err}"))?;
+        Ok(directories
+            .into_iter()
+            .filter(|relative| !relative.is_empty() && !relative.contains("node_modules"))
+            .collect())
+    };
+    let mut included = vec![];
+    let mut excluded = vec![];
+    for pattern in patterns {
+        match pattern.strip_prefix('!') {
+            Some(pattern) => excluded.extend(expand(pattern)?),
+            None => included.extend(expand(pattern)?),
+        }
+    }
+    included.sort();
+    included.dedup();
+    Ok(included
+        .into_iter()
+        .filter(|relative| !excluded.contains(relative))
+        .map(|relative| (root_dir.join(&relative).join("package.json"), relative))
+        .filter(|(manifest, _)| manifest.is_file())
+        .map(|(manifest, relative)| (manifest.to_string_lossy().to_string(), relative))
+        .collect())
+}
+
+/// Updates the ranges on bumped packages in the dependency tables found at the path of a JSON file
+pub(crate) fn set_dependency_ranges(
+    file: &str,
+    path: &[PathSegment],
+    bumped: &HashSet<String>,
+    version: &str,
+) -> Result<String, String> {
+    let json = serde_json::from_str::<serde_json::Value>(file).map_err(|err| err.to_string())?;
+    let target = path.iter().try_fold(&json, |value, segment| match segment {
+        PathSegment::Key(key) => value.get(key),
+        PathSegment::Index(index) => value.get(index),
+    });
+    let mut contents = file.to_string();
+    for table in DEPENDENCY_TABLES {
+        let Some(dependencies) = target
+            .and_then(|target| target.get(table))
+            .and_then(|dependencies| dependencies.as_object())
+        else {
+            continue;
+        };
+        for (name, range) in dependencies {
+            let Some(new_range) = range
+                .as_str()
+                .filter(|_| bumped.contains(name))
+                .and_then(|range| bump_range(range, version))
+            else {
+                continue;
+            };
+            let mut range_path = path.to_vec();
+            range_path.extend([key(table), key(name)]);
+            contents = set_json_string(&contents, &range_path, &new_range)?;
+        }
+    }
+    Ok(contents)
+}
+
+/// Updates the specifiers of the pnpm-lock.yaml importers that depend on bumped packages
+fn set_pnpm_specifiers(
+    file: &str,
+    bumped: &HashSet<String>,
+    version: &str,
+) -> Result<String, String> {
+    let yaml = serde_yaml::from_str::<serde_yaml::Value>(file)
+        .map_err(|err| format!("pnpm-lock.yaml is not valid YAML: {err}"))?;
+    let Some(importers) = yaml
+        .get("importers")
+        .and_then(|importers| importers.as_mapping())
+    else {
+        return Ok(file.to_string());
+    };
+    let mut contents = file.to_string();
+    for (importer, dependencies) in importers {
+        let Some(importer) = importer.as_str() else {
+            continue;
+        };
+        for table in DEPENDENCY_TABLES {
+            let Some(dependencies) = dependencies
+                .get(table)
+                .and_then(|dependencies| dependencies.as_mapping())
+            else {
+                continue;
+            };
+            for (name, dependency) in dependencies {
+                let Some(name) = name.as_str().filter(|name| bumped.contains(*name)) else {
+                    continue;
+                };
+                let Some(new_range) = dependency
+                    .get("specifier")
+                    .and_then(|specifier| specifier.as_str())
+                    .and_then(|specifier| bump_range(specifier, version))
+                else {
+                    continue;
+                };
+                let path = [
+                    key("importers"),
+                    key(importer),
+                    key(table),
+                    key(name),
+                    key("specifier"),
+                ];
+                contents = set_yaml_scalar(&contents, &path, &new_range)?;
+            }
+        }
+    }
+    Ok(contents)
+}
+
+/// Moves a range to the new version keeping its operator and `workspace:` protocol
+/// e.g. `^1.2.3` becomes `^1.3.0` and `workspace:~1.2.3` becomes `workspace:~1.3.0`
+/// # Returns
+/// * None for ranges that do not pin a version, like `*`, `workspace:^`, tags, other protocols
+///   or ranges made of several comparators
+fn bump_range(range: &str, version: &str) -> Option<String> {
+    let (protocol, rest) = match range.strip_prefix("workspace:") {
+        Some(rest) => ("workspace:", rest),
+        None => ("", range),
+    };
+    let current = rest.trim_start_matches(['^', '~', '=', '>', '<', ' ', 'v']);
+    let operator = &rest[..rest.len() - current.len()];
+    if !current.starts_with(|char: char| char.is_ascii_digit()) || current.contains([' ', '|']) {
+        return None;
+    }
+    let new_range = format!("{protocol}{operator}{version}");
+    (new_range != range).then_some(new_range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bump_range() {
+        assert_eq!(bump_range("^1.2.3", "1.3.0").unwrap(), "^1.3.0");
+        assert_eq!(bump_range("1.2.3", "1.3.0").unwrap(), "1.3.0");
+        assert_eq!(
+            bump_range("workspace:~1.2.3", "1.3.0").unwrap(),
+            "workspace:~1.3.0"
+        );
+        for unchanged in ["*", "workspace:^", "latest", "file:../a", ">=1.0.0 <2.0.0"] {
+            assert_eq!(bump_range(unchanged, "1.3.0"), None, "{unchanged}");
+        }
+    }
+
+    #[test]
+    fn test_plan_node_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, contents: &str| {
+            let path = dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, contents).unwrap();
+        };
+        write(
+            "package.json",
+            r#"{
+  "name": "root",
+  "version": "1.2.3",
+  "workspaces": ["packages/*", "!packages/scratch"],
+  "devDependencies": {
+    "app": "^1.2.3"
+  }
+}
+"#,
+        );
+        write(
+            "packages/app/package.json",
+            r#"{
+  "name": "app",
+  "version": "1.2.3",
+  "dependencies": {
+    "lib": "workspace:^1.2.3",
+    "left-pad": "^1.2.3"
+  }
+}
+"#,
+        );
+        write(
+            "packages/lib/package.json",
+            "{\n  \"name\": \"lib\",\n  \"version\": \"1.2.3\"\n}\n",
+        );
+        write(
+            "packages/scratch/package.json",
+            "{\n  \"name\": \"scratch\",\n  \"version\": \"0.0.1\"\n}\n",
+        );
+        write(
+            "package-lock.json",
+            r#"{
+  "name": "root",
+  "version": "1.2.3",
+  "lockfileVersion": 3,
+  "packages": {
+    "": {
+      "name": "root",
+      "version": "1.2.3",
+      "devDependencies": {
+        "app": "^1.2.3"
+      }
+    },
+    "node_modules/app": {
+      "resolved": "packages/app",
+      "link": true
+    },
+    "node_modules/left-pad": {
+      "version": "1.2.3"
+    },
+    "packages/app": {
+      "name": "app",
+      "version": "1.2.3",
+      "dependencies": {
+        "left-pad": "^1.2.3",
+        "lib": "workspace:^1.2.3"
+      }
+    }
+  }
+}
+"#,
+        );
+
+        let root = dir
+            .path()
+            .join("package.json")
+            .to_string_lossy()
+            .to_string();
+        let read = |path: &str| std::fs::read_to_string(path).map_err(|err| err.to_string());
+        let planned = plan_node_sync(&root, "1.3.0", &read).unwrap();
+        let planned = planned
+            .into_iter()
+            .map(|(path, contents)| {
+                let path = Path::new(&path).strip_prefix(dir.path()).unwrap();
+                (path.to_string_lossy().to_string(), contents)
+            })
+            .collect::<Vec<(String, String)>>();
+        let files = planned
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            files,
+            vec![
+                "package.json",
+                "packages/app/package.json",
+                "packages/lib/package.json",
+                "package-lock.json"
+            ]
+        );
+
+        assert_eq!(planned[0].1, read(&root).unwrap().replace("1.2.3", "1.3.0"));
+        assert!(planned[1].1.contains("\"version\": \"1.3.0\""));
+        assert!(planned[1].1.contains("\"lib\": \"workspace:^1.3.0\""));
+        assert!(planned[1].1.contains("\"left-pad\": \"^1.2.3\""));
+        assert!(planned[2].1.contains("\"version\": \"1.3.0\""));
+
+        let lock = &planned[3].1;
+        assert!(lock.contains("\"name\": \"root\",\n  \"version\": \"1.3.0\""));
+        assert!(lock.contains("\"name\": \"root\",\n      \"version\": \"1.3.0\""));
+        assert!(lock.contains("\"app\": \"^1.3.0\""));
+        assert!(lock.contains("\"name\": \"app\",\n      \"version\": \"1.3.0\""));
+        assert!(lock.contains("\"lib\": \"workspace:^1.3.0\""));
+        assert!(lock.contains("\"node_modules/left-pad\": {\n      \"version\": \"1.2.3\""));
+        assert!(lock.contains("\"left-pad\": \"^1.2.3\""));
+    }
+
+    #[test]
+    fn test_set_pnpm_specifiers() {
+        let lock = r#"lockfileVersion: '9.0'
+
+importers:
+
+  .:
+    devDependencies:
+      '@scope/app':
+        specifier: workspace:^1.2.3
+        version: link:packages/app
+
+  packages/app:
+    dependencies:
+      left-pad:
+        specifier: ^1.2.3
+        version: 1.2.3
+"#;
+        let bumped = HashSet::from(["@scope/app".to_string()]);
+        let updated = set_pnpm_specifiers(lock, &bumped, "1.3.0").unwrap();
+        assert_eq!(
+            updated,
+            lock.replace("workspace:^1.2.3", "workspace:^1.3.0")
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Release branches, one per release line e.g. `release/1.2.x`
+//!
+//! A release branch is created from the release commit with `git branch`, so the checkout is left
+//! on the branch the release was made from. Its name follows `Config::release_branch_pattern`,
+//! and on a release branch only patch releases are made, keeping the line on its major and minor.
+use regex::Regex;
+
+use crate::config::Config;
+use crate::error::{GitScribeError, Result};
+use crate::git::run_git;
+use crate::version::{Version, VersionDesignation};
+use crate::EXPECTED_CONFIG_FILE_NAME;
+
+/// The placeholders of a branch pattern, with the pattern of the version part they stand for
+const PLACEHOLDERS: [(&str, &str); 4] = [
+    ("{version}", r"\d+\.\d+\.\d+"),
+    ("{major}", r"\d+"),
+    ("{minor}", r"\d+"),
+    ("{patch}", r"\d+"),
+];
+
+/// The outcome of creating a release branch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReleaseBranch {
+    Created(String),
+    /// The branch was already there and was left unchanged
+    Exists(String),
+}
+
+/// A release line, the release branch and the version in its config file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseLine {
+    pub branch: String,
+    pub version: Option<String>,
+}
+
+/// Fills in the placeholders of a branch pattern with the version
+/// # Arguments
+/// * `pattern` - The branch pattern e.g. `release/{major}.{minor}.x`
+/// * `version` - The release version
+pub fn release_branch_name(pattern: &str, version: &Version) -> String {
+    pattern
+        .replace("{version}", &version.to_string())
+        .replace("{major}", &version.major.to_string())
+        .replace("{minor}", &version.minor.to_string())
+        .replace("{patch}", &version.patch.to_string())
+}
+
+/// Returns true if the branch name is a release branch of the pattern
+pub fn is_release_branch(pattern: &str, branch: &str) -> bool {
+    let mut regex = regex::escape(pattern);
+    for (placeholder, part) in PLACEHOLDERS {
+        regex = regex.replace(&regex::escape(placeholder), part);
+    }
+    Regex::new(&format!("^{regex}$")).is_ok_and(|regex| regex.is_match(branch))
+}
+
+/// Checks the release is allowed on the branch, only patch releases are made on a release branch
+/// # Errors
+/// * A branch policy error for a major or minor release on a release branch
+pub fn check_release_branch(
+    config: &Config,
+    branch: Option<&str>,
+    version_designation: &VersionDesignation,
+) -> Result<()> {
+    match branch {
+        Some(branch)
+            if *version_designation != VersionDesignation::Patch
+                && is_release_branch(&config.release_branch_pattern(), branch) =>
+        {
+            Err(GitScribeError::BranchPolicy(format!(
+                "{branch} is a release branch, only patch releases can be made on it"
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Creates the release branch of the version at the checked out commit, without switching to it
+/// # Returns
+/// * Whether the branch was created or already existed
+/// # Errors
+/// * A git error if the branch cannot be created
+pub fn create_release_branch(config: &Config, version: &Version) -> Result<ReleaseBranch> {
+    let branch = release_branch_name(&config.release_branch_pattern(), version);
+    let exists = run_git(&[
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("refs/heads/{branch}"),
+    ])
+    .is_ok();
+    if exists {
+        return Ok(ReleaseBranch::Exists(branch));
+    }
+    run_git(&["branch", &branch, "HEAD"]).map_err(|err| {
+        GitScribeError::Git(format!("Failed to create the branch {branch}: {err}"))
+    })?;
+    Ok(ReleaseBranch::Created(branch))
+}
+
+/// Lists the release branches, local and remote, with the version each is at, newest first
+/// # Errors
+/// * A git error if the branches cannot be listed
+pub fn release_lines(config: &Config) -> Result<Vec<ReleaseLine>> {
+    let pattern = config.release_branch_pattern();
+    let refs = run_git(&[
+        "for-each-ref",
+        "--format=%(refname)",
+        "refs/heads",
+        "refs/remotes",
+    ])?;
+    let mut lines: Vec<ReleaseLine> = vec![];
+    for refname in refs.lines() {
+        // remote branches are named without the remote, local branches are listed first and win
+        let branch = match refname.strip_prefix("refs/heads/") {
+            Some(branch) => branch,
+            None => match refname
+                .strip_prefix("refs/remotes/")
+                .and_then(|remote| remote.split_once('/'))
+            {
+                Some((_, branch)) => branch,
+                None => continue,
+            },
+        };
+        if !is_release_branch(&pattern, branch) || lines.iter().any(|line| line.branch == branch) {
+            continue;
+        }
+        let version = run_git(&["show", &format!("{refname}:{EXPECTED_CONFIG_FILE_NAME}")])
+            .ok()
+            .and_then(|config| serde_json::from_str::<serde_json::Value>(&config).ok())
+            .and_then(|config| {
+                config
+                    .get("version")?
+                    .as_str()
+                    .map(|version| version.to_string())
+            });
+        lines.push(ReleaseLine {
+            branch: branch.to_string(),
+            version,
+        });
+    }
+    lines.sort_by_key(|line| {
+        std::cmp::Reverse(
+            line.version
+                .clone()
+                .and_then(|version| Version::new(version).ok()),
+        )
+    });
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_branch_names() {
+        let version = Version::new("1.4.0".to_string()).unwrap();
+        assert_eq!(
+            release_branch_name("release/{major}.{minor}.x", &version),
+            "release/1.4.x"
+        );
+        assert_eq!(release_branch_name("v{version}", &version), "v1.4.0");
+        assert!(is_release_branch(
+            "release/{major}.{minor}.x",
+            "release/1.4.x"
+        ));
+        assert!(!is_release_branch(
+            "release/{major}.{minor}.x",
+            "release/1.4.2"
+        ));
+        assert!(!is_release_branch("release/{major}.{minor}.x", "main"));
+
+        let mut config = Config::create_default();
+        config.commands_that_release = vec!["minor".to_string(), "major".to_string()];
+        assert_eq!(config.release_branch_pattern(), "release/{major}.{minor}.X");
+        assert!(
+            check_release_branch(&config, Some("release/1.4.X"), &VersionDesignation::Patch)
+                .is_ok()
+        );
+        assert!(matches!(
+            check_release_branch(&config, Some("release/1.4.X"), &VersionDesignation::Minor),
+            Err(GitScribeError::BranchPolicy(_))
+        ));
+        assert!(check_release_branch(&config, Some("main"), &VersionDesignation::Major).is_ok());
+    }
+}
@@ -0,0 +1,221 @@
+use std::fs;
+use std::io::{Error, Write};
+use std::path::{Path, PathBuf};
+
+/// A file write that has been computed in memory but not yet applied to disk
+#[derive(Debug, Clone)]
+struct StagedWrite {
+    path: PathBuf,
+    contents: String,
+}
+
+/// The state of a file before the release touched it
+/// `original` is `None` when the file did not exist yet
+#[derive(Debug, Clone)]
+struct Snapshot {
+    path: PathBuf,
+    original: Option<Vec<u8>>,
+}
+
+/// Collects every file a release writes so they can be applied together
+/// and restored if any later step of the release fails
+///
+/// # Examples
+/// ```no_run
+/// use gitscribe::transaction::ReleaseTransaction;
+/// let mut transaction = ReleaseTransaction::new();
+/// transaction.stage("VERSION", "1.0.1\n".to_string());
+/// transaction.apply().unwrap();
+/// // something went wrong later on
+/// transaction.rollback().unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct ReleaseTransaction {
+    staged: Vec<StagedWrite>,
+    snapshots: Vec<Snapshot>,
+    /// The number of staged writes already applied
+    applied: usize,
+}
+
+impl ReleaseTransaction {
+    /// Creates an empty transaction
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stages the new contents of a file, replacing anything staged for the same path
+    /// # Arguments
+    /// * `path` - The path of the file to write
+    /// * `contents` - The full new contents of the file
+    pub fn stage(&mut self, path: impl AsRef<Path>, contents: String) {
+        let path = path.as_ref().to_path_buf();
+        // a file already written is written again by the next apply
+        match self.staged[self.applied..]
+            .iter_mut()
+            .find(|write| write.path == path)
+        {
+            Some(write) => write.contents = contents,
+            None => self.staged.push(StagedWrite { path, contents }),
+        }
+    }
+
+    /// Returns the paths of every staged file in the order they were staged
+    pub fn staged_paths(&self) -> Vec<&Path> {
+        let mut paths: Vec<&Path> = vec![];
+        for write in &self.staged {
+            if !paths.contains(&write.path.as_path()) {
+                paths.push(&write.path);
+            }
+        }
+        paths
+    }
+
+    /// Writes every staged file atomically, taking a snapshot of each file first
+    /// Files staged after an earlier `apply` are written by the next one, so a release can be written in steps.
+    /// If any write fails, the files already written are restored before returning the error
+    pub fn apply(&mut self) -> Result<(), Error> {
+        let pending = self.staged[self.applied..].to_vec();
+        self.applied = self.staged.len();
+        for write in pending {
+            let original = match fs::read(&write.path) {
+                Ok(bytes) => Some(bytes),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                Err(err) => {
+                    self.rollback()?;
+                    return Err(err);
+                }
+            };
+            self.snapshots.push(Snapshot {
+                path: write.path.clone(),
+                original,
+            });
+            if let Err(err) = write_atomic(&write.path, write.contents.as_bytes()) {
+                self.rollback()?;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Restores every file written by `apply` to its original state
+    /// Files that did not exist before the release are removed
+    pub fn rollback(&mut self) -> Result<(), Error> {
+        while let Some(snapshot) = self.snapshots.pop() {
+            match snapshot.original {
+                Some(bytes) => write_atomic(&snapshot.path, &bytes)?,
+                None => {
+                    if snapshot.path.exists() {
+                        fs::remove_file(&snapshot.path)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the contents to a temporary file next to the target and renames it into place,
+/// so the target is never left partially written
+/// # Arguments
+/// * `path` - The file to write
+/// * `contents` - The bytes to write
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| Error::other(format!("{} is not a file path", path.display())))?;
+    let temp_path = path.with_file_name(format!(".{}.gitscribe-tmp", file_name.to_string_lossy()));
+
+    let result = fs::File::create(&temp_path).and_then(|mut file| {
+        file.write_all(contents)?;
+        file.sync_all()
+    });
+    match result.and_then(|_| fs::rename(&temp_path, path)) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(err)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_writes_staged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("gitscribe.json");
+        let created = dir.path().join("CHANGELOG.md");
+        fs::write(&existing, "old").unwrap();
+
+        let mut transaction = ReleaseTransaction::new();
+        transaction.stage(&existing, "first".to_string());
+        transaction.stage(&created, "changelog".to_string());
+        transaction.stage(&existing, "new".to_string());
+        transaction.apply().unwrap();
+
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "new");
+        assert_eq!(fs::read_to_string(&created).unwrap(), "changelog");
+        assert_eq!(transaction.staged_paths().len(), 2);
+    }
+
+    #[test]
+    fn test_rollback_restores_originals() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("Cargo.toml");
+        let created = dir.path().join("CHANGELOG.md");
+        fs::write(&existing, "version = \"0.0.1\"").unwrap();
+
+        let mut transaction = ReleaseTransaction::new();
+        transaction.stage(&existing, "version = \"0.0.2\"".to_string());
+        transaction.stage(&created, "changelog".to_string());
+        transaction.apply().unwrap();
+        transaction.rollback().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&existing).unwrap(),
+            "version = \"0.0.1\""
+        );
+        assert!(!created.exists());
+    }
+
+    #[test]
+    fn test_failed_apply_restores_written_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let existing = dir.path().join("gitscribe.json");
+        fs::write(&existing, "old").unwrap();
+
+        let mut transaction = ReleaseTransaction::new();
+        transaction.stage(&existing, "new".to_string());
+        transaction.stage(
+            dir.path().join("missing_dir").join("CHANGELOG.md"),
+            "changelog".to_string(),
+        );
+
+        assert!(transaction.apply().is_err());
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "old");
+    }
+
+    #[test]
+    fn test_apply_in_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("gitscribe.json");
+        let changelog = dir.path().join("CHANGELOG.md");
+        fs::write(&config, "old").unwrap();
+
+        let mut transaction = ReleaseTransaction::new();
+        transaction.stage(&config, "new".to_string());
+        transaction.apply().unwrap();
+        // a later step changes the file again, and adds another
+        transaction.stage(&config, "newer".to_string());
+        transaction.stage(&changelog, "changelog".to_string());
+        transaction.apply().unwrap();
+
+        assert_eq!(fs::read_to_string(&config).unwrap(), "newer");
+        assert_eq!(transaction.staged_paths(), vec![&config, &changelog]);
+        transaction.rollback().unwrap();
+        assert_eq!(fs::read_to_string(&config).unwrap(), "old");
+        assert!(!changelog.exists());
+    }
+}
@@ -1,10 +1,15 @@
+use crate::config::BranchPolicy;
+use crate::error::{GitScribeError, Result};
+use crate::git::{current_branch, run_git};
+use crate::version::VersionDesignation;
 use colored::Colorize;
+use globset::{Glob, GlobBuilder};
 use regex::Regex;
+use std::path::Path;
 /// Prints the banner
 /// # Examples
 /// ```
-/// use gitscribe;
-/// gitscribe::print_banner();
+/// gitscribe::util::print_banner();
 /// ```
 pub fn print_banner() {
     let banner = r#"
@@ -18,56 +23,270 @@ pub fn print_banner() {
 }
 
 /// Checks if there are uncommitted changes
-/// if there are, it will print the changes and exit
-pub fn check_for_uncommitted_changes() {
-    let output = std::process::Command::new("git")
-        .args(&["status", "--porcelain"])
-        .output()
-        .expect("Failed to execute command");
-    if output.stdout.len() > 0 {
-        println!(
-            "{}",
-            "🛑There Are Uncommitted Changes, please commit before trying again:"
-                .red()
-                .underline()
-        );
-        let uncommitted_changes = String::from_utf8_lossy(&output.stdout);
+/// # Errors
+/// * An `UncommittedChanges` error listing each `git status --porcelain` line if the working tree is not clean
+/// * A git error if git status fails
+pub fn check_for_uncommitted_changes() -> Result<()> {
+    let output = run_git(&["status", "--porcelain"])?;
+    //get list of uncommitted changes
+    let uncommitted_changes = output
+        .split('\n')
+        .filter(|change| !change.is_empty())
+        .map(|change| change.to_string())
+        .collect::<Vec<String>>();
+    if uncommitted_changes.is_empty() {
+        Ok(())
+    } else {
+        Err(GitScribeError::UncommittedChanges(uncommitted_changes))
+    }
+}
+
+/// Checks the release is allowed by the branch policy
+///
+/// The branch must match one of the patterns of the designation, and unless turned off in the policy,
+/// be neither behind nor ahead of its upstream as of the last fetch. Branches without an upstream skip the upstream checks
+/// # Arguments
+/// * `policy` - The branch policy of the config, nothing is checked without one
+/// * `version_designation` - The version designation being released
+/// # Errors
+/// * A branch policy error naming the rule the checked out branch breaks
+/// * A config error if a pattern is not a valid glob
+/// * A git error if git fails
+pub fn check_branch_policy(
+    policy: Option<&BranchPolicy>,
+    version_designation: &VersionDesignation,
+) -> Result<()> {
+    let Some(policy) = policy else {
+        return Ok(());
+    };
+    let branch = current_branch()?;
+    let allowed = policy.allowed_branches(version_designation);
+    if !allowed.is_empty() {
+        let Some(branch) = &branch else {
+            return Err(GitScribeError::BranchPolicy(format!(
+                "HEAD is detached, {version_designation} releases are only allowed from {}",
+                allowed.join(", ")
+            )));
+        };
+        if !branch_matches(allowed, branch)? {
+            return Err(GitScribeError::BranchPolicy(format!(
+                "{version_designation} releases are only allowed from {}, not {branch}",
+                allowed.join(", ")
+            )));
+        }
+    }
+
+    if branch.is_none() || !(policy.require_up_to_date || policy.require_pushed) {
+        return Ok(());
+    }
+    let Ok(upstream) = run_git(&[
+        "rev-parse",
+        "--abbrev-ref",
+        "--symbolic-full-name",
+        "@{upstream}",
+    ]) else {
+        return Ok(());
+    };
+    let upstream = upstream.trim();
+    let counts = run_git(&["rev-list", "--left-right", "--count", "HEAD...@{upstream}"])?;
+    let (ahead, behind) = counts
+        .split_once('\t')
+        .and_then(|(ahead, behind)| {
+            Some((
+                ahead.trim().parse::<u32>().ok()?,
+                behind.trim().parse::<u32>().ok()?,
+            ))
+        })
+        .ok_or_else(|| {
+            GitScribeError::Git(format!("Unexpected `git rev-list` output: {counts}"))
+        })?;
+    if policy.require_up_to_date && behind > 0 {
+        return Err(GitScribeError::BranchPolicy(format!(
+            "the branch is {behind} commits behind {upstream}, pull before releasing"
+        )));
+    }
+    if policy.require_pushed && ahead > 0 {
+        return Err(GitScribeError::BranchPolicy(format!(
+            "the branch has {ahead} commits that are not pushed to {upstream}, push before releasing"
+        )));
+    }
+    Ok(())
+}
 
-        //get list of uncommitted changes
-        let uncommitted_changes = uncommitted_changes
-            .split("\n")
-            .filter(|change| change.len() > 0)
-            .map(|change| change.to_string())
-            .collect::<Vec<String>>();
+/// Returns true if the branch matches one of the glob patterns e.g. `release/*`
+/// # Errors
+/// * A config error if a pattern is not a valid glob
+fn branch_matches(patterns: &[String], branch: &str) -> Result<bool> {
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|err| {
+            GitScribeError::Config(format!("Invalid branch pattern '{pattern}': {err}"))
+        })?;
+        if glob.compile_matcher().is_match(branch) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
 
-        // parsing for pattern https://git-scm.com/docs/git-status#_output
-        let re = Regex::new(r"(?<prefix>[M,T,A,R,D,C,U,\?])(?<change>.*$)").unwrap();
+/// Finds the directories matching a glob relative to the root directory, e.g. the members of a workspace
+///
+/// `*` stays within one directory and `**` spans any number, node_modules and .git are never searched
+/// # Arguments
+/// * `root_dir` - The directory the pattern is relative to
+/// * `pattern` - The glob e.g. `crates/*`, a leading `./` and a trailing `/` are ignored
+/// # Returns
+/// * The matching directories relative to the root directory joined with `/`, sorted, or the reason the glob is invalid
+pub(crate) fn glob_directories(
+    root_dir: &Path,
+    pattern: &str,
+) -> std::result::Result<Vec<String>, String> {
+    let components = pattern
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect::<Vec<&str>>();
+    // walk from the part of the pattern without wildcards
+    let literal_len = components
+        .iter()
+        .take_while(|component| !component.contains(['*', '?', '[', '{']))
+        .count();
+    let prefix = components[..literal_len].join("/");
+    let base = root_dir.join(&prefix);
+    if literal_len == components.len() {
+        return Ok(match base.is_dir() {
+            true => vec![prefix],
+            false => vec![],
+        });
+    }
+    let rest = &components[literal_len..];
+    let matcher = GlobBuilder::new(&rest.join("/"))
+        .literal_separator(true)
+        .build()
+        .map_err(|err| err.to_string())?
+        .compile_matcher();
+    let max_depth = match rest.contains(&"**") {
+        true => usize::MAX,
+        false => rest.len(),
+    };
 
-        for change in uncommitted_changes {
-            let captures = match re.captures(change.as_str()) {
-                Some(captures) => captures,
-                None => {
-                    println!("{}", change);
-                    continue;
-                }
+    let mut matches = vec![];
+    let mut pending = vec![(base, String::new(), 0)];
+    while let Some((directory, relative, depth)) = pending.pop() {
+        if depth == max_depth {
+            continue;
+        }
+        let Ok(entries) = std::fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !entry.path().is_dir() || name == "node_modules" || name == ".git" {
+                continue;
+            }
+            let child = match relative.is_empty() {
+                true => name,
+                false => format!("{relative}/{name}"),
             };
+            if matcher.is_match(&child) {
+                matches.push(match prefix.is_empty() {
+                    true => child.clone(),
+                    false => format!("{prefix}/{child}"),
+                });
+            }
+            pending.push((entry.path(), child, depth + 1));
+        }
+    }
+    matches.sort();
+    Ok(matches)
+}
+
+/// Prints the uncommitted changes found by `check_for_uncommitted_changes`
+/// # Arguments
+/// * `uncommitted_changes` - The `git status --porcelain` lines
+pub fn print_uncommitted_changes(uncommitted_changes: &[String]) {
+    println!(
+        "{}",
+        "🛑There Are Uncommitted Changes, please commit before trying again:"
+            .red()
+            .underline()
+    );
+
+    // parsing for pattern https://git-scm.com/docs/git-status#_output
+    let re = Regex::new(r"(?<prefix>[M,T,A,R,D,C,U,\?])(?<change>.*$)").unwrap();
+
+    for change in uncommitted_changes {
+        let captures = match re.captures(change.as_str()) {
+            Some(captures) => captures,
+            None => {
+                println!("{}", change);
+                continue;
+            }
+        };
+
+        println!(
+            "{} {}",
+            match captures.name("prefix").unwrap().as_str() {
+                "M" => "Modified:".cyan(),
+                "T" => "File Type Changed:".cyan(),
+                "A" => "Added:".green(),
+                "R" => "Renamed:".yellow(),
+                "D" => "Deleted:".red(),
+                "C" => "Copied:".green(),
+                "U" => "Unmerged:".red(),
+                "?" => "Untracked:".purple(),
+                _ => "Unknown:".red(),
+            },
+            captures.name("change").unwrap().as_str()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            println!(
-                "{} {}",
-                match captures.name("prefix").unwrap().as_str() {
-                    "M" => "Modified:".cyan(),
-                    "T" => "File Type Changed:".cyan(),
-                    "A" => "Added:".green(),
-                    "R" => "Renamed:".yellow(),
-                    "D" => "Deleted:".red(),
-                    "C" => "Copied:".green(),
-                    "U" => "Unmerged:".red(),
-                    "?" => "Untracked:".purple(),
-                    _ => "Unknown:".red(),
-                },
-                captures.name("change").unwrap().as_str()
-            );
+    #[test]
+    fn test_glob_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        for directory in [
+            "crates/core",
+            "crates/cli/src",
+            "packages/ui/node_modules/dep",
+            "tools",
+        ] {
+            std::fs::create_dir_all(dir.path().join(directory)).unwrap();
         }
-        std::process::exit(1);
+        std::fs::write(dir.path().join("crates/README.md"), "").unwrap();
+
+        assert_eq!(
+            glob_directories(dir.path(), "./crates/*/").unwrap(),
+            vec!["crates/cli", "crates/core"]
+        );
+        assert_eq!(
+            glob_directories(dir.path(), "crates/**").unwrap(),
+            vec!["crates/cli", "crates/cli/src", "crates/core"]
+        );
+        assert_eq!(
+            glob_directories(dir.path(), "packages/**").unwrap(),
+            vec!["packages/ui"]
+        );
+        assert_eq!(
+            glob_directories(dir.path(), "tools").unwrap(),
+            vec!["tools"]
+        );
+        assert!(glob_directories(dir.path(), "missing").unwrap().is_empty());
+        assert!(glob_directories(dir.path(), "crates/[").is_err());
+    }
+
+    #[test]
+    fn test_branch_matches() {
+        let patterns = vec!["main".to_string(), "release/*".to_string()];
+        assert!(branch_matches(&patterns, "main").unwrap());
+        assert!(branch_matches(&patterns, "release/1.4.X").unwrap());
+        assert!(!branch_matches(&patterns, "feature/login").unwrap());
+        assert!(!branch_matches(&patterns, "maintenance").unwrap());
+        assert!(matches!(
+            branch_matches(&["release/[".to_string()], "main"),
+            Err(GitScribeError::Config(_))
+        ));
     }
 }
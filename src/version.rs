@@ -1,11 +1,9 @@
 use crate::config::Config;
-use crate::EXPECTED_CONFIG_FILE_NAME;
-use colored::Colorize;
+use crate::error::{GitScribeError, Result};
 use core::fmt;
-use std::fs::OpenOptions;
-use std::io::Write;
 
 /// Enum representing the different version designations
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum VersionDesignation {
     Major,
     Minor,
@@ -20,8 +18,8 @@ impl std::fmt::Display for VersionDesignation {
         }
     }
 }
-/// Struct representing the version
-#[derive(Clone)]
+/// Struct representing the version, ordered by semver precedence
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Version {
     pub major: i32,
     pub minor: i32,
@@ -31,13 +29,53 @@ impl Version {
     /// Creates a new instance of the Version struct from a string
     /// # Arguments
     /// * `version` - The version string ex. "0.0.1"
-    pub fn new(version: String) -> Self {
-        let version = version.split(".").collect::<Vec<&str>>();
-        Version {
-            major: version[0].parse().unwrap(), // if unwrap fails, the program will panic w
-            minor: version[1].parse().unwrap(),
-            patch: version[2].parse().unwrap(),
+    /// # Errors
+    /// * If the string is not three dot separated numbers
+    pub fn new(version: String) -> Result<Self> {
+        let parts = version.trim().split('.').collect::<Vec<&str>>();
+        let invalid = || {
+            GitScribeError::Parse(format!(
+                "'{version}' is not a valid version, expected MAJOR.MINOR.PATCH"
+            ))
+        };
+        if parts.len() != 3 {
+            return Err(invalid());
         }
+        Ok(Version {
+            major: parts[0].parse().map_err(|_| invalid())?,
+            minor: parts[1].parse().map_err(|_| invalid())?,
+            patch: parts[2].parse().map_err(|_| invalid())?,
+        })
+    }
+}
+impl Version {
+    /// Returns the next version for the designation, without changing this one
+    /// # Arguments
+    /// * `version_designation` - The version designation
+    pub fn bump(&self, version_designation: &VersionDesignation) -> Version {
+        let mut version = self.clone();
+        match version_designation {
+            VersionDesignation::Major => {
+                version.major += 1;
+                version.minor = 0;
+                version.patch = 0;
+            }
+            VersionDesignation::Minor => {
+                version.minor += 1;
+                version.patch = 0;
+            }
+            VersionDesignation::Patch => {
+                version.patch += 1;
+            }
+        }
+        version
+    }
+}
+impl std::str::FromStr for Version {
+    type Err = GitScribeError;
+
+    fn from_str(version: &str) -> Result<Self> {
+        Version::new(version.to_string())
     }
 }
 impl std::fmt::Display for Version {
@@ -51,64 +89,12 @@ impl std::fmt::Display for Version {
 /// * `config` - The config struct
 /// * `version_designation` - The version designation
 /// # Returns
-/// * The new version
-pub fn increment_version(config: &Config, version_designation: &VersionDesignation) -> Version {
-    let mut version = Version::new(config.version.clone());
-    match version_designation {
-        VersionDesignation::Major => {
-            version.major += 1;
-            version.minor = 0;
-            version.patch = 0;
-        }
-        VersionDesignation::Minor => {
-            version.minor += 1;
-            version.patch = 0;
-        }
-        VersionDesignation::Patch => {
-            version.patch += 1;
-        }
-    }
-
-    println!(
-        "{} {}.{}.{}",
-        "New version:".underline(),
-        version.major.to_string().green(),
-        version.minor.to_string().green(),
-        version.patch.to_string().green()
-    );
-
-    version
-}
-
-/// Writes the new version to the config file and returns the new config
-/// # Arguments
-/// * `config` - The config struct
-/// * `version` - The new version
-/// # Returns
-/// * The new config
-pub fn write_new_version_to_file(config: Config, version: Version) -> Config {
-    // write new version of config to config file
-    let mut new_config = config;
-    new_config.version = format!("{}.{}.{}", version.major, version.minor, version.patch);
-    let new_config_string = serde_json::to_string_pretty(&new_config).unwrap();
-    let file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(EXPECTED_CONFIG_FILE_NAME);
-
-    match file {
-        Ok(mut file) => {
-            file.write_all(new_config_string.as_bytes())
-                .unwrap_or_else(|_| {
-                    println!("Failed to write to file");
-                });
-        }
-        Err(_) => {
-            println!("Failed to open file");
-        }
-    }
-
-    new_config
+/// * The new version, or a parse error if the configured version is invalid
+pub fn increment_version(
+    config: &Config,
+    version_designation: &VersionDesignation,
+) -> Result<Version> {
+    Ok(Version::new(config.version.clone())?.bump(version_designation))
 }
 
 #[cfg(test)]
@@ -117,16 +103,22 @@ mod tests {
 
     #[test]
     fn test_version() {
-        let version = Version::new("0.0.1".to_string());
+        let version = Version::new("0.0.1".to_string()).unwrap();
         assert_eq!(version.major, 0);
         assert_eq!(version.minor, 0);
         assert_eq!(version.patch, 1);
     }
 
+    #[test]
+    fn test_invalid_version() {
+        assert!(Version::new("1.2".to_string()).is_err());
+        assert!(Version::new("1.two.3".to_string()).is_err());
+    }
+
     #[test]
     fn test_increment_version_patch() {
         let config = Config::create_default();
-        let version = increment_version(&config, &VersionDesignation::Patch);
+        let version = increment_version(&config, &VersionDesignation::Patch).unwrap();
         assert_eq!(version.major, 0);
         assert_eq!(version.minor, 0);
         assert_eq!(version.patch, 2);
@@ -135,7 +127,7 @@ mod tests {
     #[test]
     fn test_increment_version_minor() {
         let config = Config::create_default();
-        let version = increment_version(&config, &VersionDesignation::Minor);
+        let version = increment_version(&config, &VersionDesignation::Minor).unwrap();
         assert_eq!(version.major, 0);
         assert_eq!(version.minor, 1);
         assert_eq!(version.patch, 0);
@@ -145,7 +137,7 @@ mod tests {
     fn test_increment_version_major() {
         let mut config = Config::create_default();
         config.version = "0.1.1".to_string();
-        let version = increment_version(&config, &VersionDesignation::Major);
+        let version = increment_version(&config, &VersionDesignation::Major).unwrap();
         assert_eq!(version.major, 1);
         assert_eq!(version.minor, 0);
         assert_eq!(version.patch, 0);
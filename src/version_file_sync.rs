@@ -1,18 +1,59 @@
-use std::io::Error;
-
+use indexmap::IndexMap;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::cargo_workspace::{
+    plan_cargo_workspace_sync, read_cargo_workspace_versions, read_package_version,
+    read_workspace_version,
+};
+use crate::edit::{
+    display_key_path, parse_key_path, set_json_string, set_yaml_scalar, PathSegment,
+};
+use crate::error::{GitScribeError, Result};
+use crate::node_workspace::{plan_node_sync, read_node_version, read_node_versions};
+use crate::transaction::ReleaseTransaction;
+
 /// Enum Representing the supported file formats for the version sync file
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub enum SupportedSyncFileFormat {
     Json,
     CargoToml,
     PoetryToml,
+    PyprojectToml,
     Yaml,
+    CargoWorkspace,
+    Node,
+    Regex,
+    PlainText,
 }
 impl SupportedSyncFileFormat {
     pub fn values() -> Vec<Self> {
-        vec![Self::Json, Self::CargoToml, Self::PoetryToml, Self::Yaml]
+        vec![
+            Self::Json,
+            Self::CargoToml,
+            Self::PoetryToml,
+            Self::PyprojectToml,
+            Self::Yaml,
+            Self::CargoWorkspace,
+            Self::Node,
+            Self::Regex,
+            Self::PlainText,
+        ]
+    }
+
+    /// The table holding the version key for the TOML formats, None for the other formats
+    fn toml_table_path(&self) -> Option<&'static [&'static str]> {
+        match self {
+            SupportedSyncFileFormat::CargoToml => Some(&["package"]),
+            SupportedSyncFileFormat::PoetryToml => Some(&["tool", "poetry"]),
+            SupportedSyncFileFormat::PyprojectToml => Some(&["project"]),
+            SupportedSyncFileFormat::Json
+            | SupportedSyncFileFormat::Yaml
+            | SupportedSyncFileFormat::CargoWorkspace
+            | SupportedSyncFileFormat::Node
+            | SupportedSyncFileFormat::Regex
+            | SupportedSyncFileFormat::PlainText => None,
+        }
     }
 }
 impl std::fmt::Display for SupportedSyncFileFormat {
@@ -21,82 +62,966 @@ impl std::fmt::Display for SupportedSyncFileFormat {
             SupportedSyncFileFormat::Json => write!(f, "Json"),
             SupportedSyncFileFormat::CargoToml => write!(f, "CargoToml"),
             SupportedSyncFileFormat::PoetryToml => write!(f, "PoetryToml"),
+            SupportedSyncFileFormat::PyprojectToml => write!(f, "PyprojectToml"),
             SupportedSyncFileFormat::Yaml => write!(f, "Yaml"),
+            SupportedSyncFileFormat::CargoWorkspace => write!(f, "CargoWorkspace"),
+            SupportedSyncFileFormat::Node => write!(f, "Node"),
+            SupportedSyncFileFormat::Regex => write!(f, "Regex"),
+            SupportedSyncFileFormat::PlainText => write!(f, "PlainText"),
         }
     }
 }
 
+impl std::str::FromStr for SupportedSyncFileFormat {
+    type Err = GitScribeError;
+
+    /// Parses a sync file format by name, ignoring case. e.g. `cargotoml` or `Json`
+    fn from_str(value: &str) -> Result<Self> {
+        Self::values()
+            .into_iter()
+            .find(|format| format.to_string().eq_ignore_ascii_case(value.trim()))
+            .ok_or_else(|| {
+                GitScribeError::Parse(format!(
+                    "Unknown sync file format '{value}', expected one of: {}",
+                    Self::values()
+                        .iter()
+                        .map(|format| format.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ))
+            })
+    }
+}
+
 /// Struct Representing the file which other package managers use to store the version number that needs to be updated by gitscribe
+///
+/// A version key is a dotted path with optional array indexes e.g. `spec.containers[0].image`.
+/// For the TOML formats a plain key like `version` is looked up in the format's table,
+/// while a dotted key like `workspace.package.version` starts at the root of the document.
+/// For the Regex format the key is a pattern whose `version` group, or else its first group, holds the version
+/// e.g. `__version__ = "(.+)"`, and the PlainText format ignores the key as the whole file is the version.
+/// The CargoWorkspace format also ignores the key, its path is the root Cargo.toml of a workspace and
+/// the members, path dependency requirements and Cargo.lock are updated along with it.
+/// Likewise the Node format takes the root package.json and also updates its workspaces and lockfiles
+/// # Fields
+/// * `version_key` - The key holding the version
+/// * `additional_version_keys` - Other keys in the same file that hold the version e.g. `appVersion`
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct VersionSyncFile {
     pub file_format: SupportedSyncFileFormat,
     pub file_path: String,
     pub version_key: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_version_keys: Vec<String>,
+}
+
+impl VersionSyncFile {
+    /// Every key of the file that holds the version, starting with `version_key`
+    pub fn version_keys(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.version_key).chain(self.additional_version_keys.iter())
+    }
+
+    /// Parses a version key into the path from the root of the document
+    fn key_path(&self, version_key: &str) -> Result<Vec<PathSegment>> {
+        let path = parse_key_path(version_key).map_err(|reason| invalid(self, reason))?;
+        match (self.file_format.toml_table_path(), path.as_slice()) {
+            (Some(table_path), [PathSegment::Key(_)]) => Ok(table_path
+                .iter()
+                .map(|key| PathSegment::Key(key.to_string()))
+                .chain(path)
+                .collect()),
+            _ => Ok(path),
+        }
+    }
+}
+
+impl std::str::FromStr for VersionSyncFile {
+    type Err = GitScribeError;
+
+    /// Parses a `FORMAT:PATH[:KEY[,KEY...]]` spec, the key defaults to `version`. e.g. `Yaml:./Chart.yaml:version,appVersion`
+    /// For the Regex format everything after the path is a single pattern e.g. `Regex:./main.go:VERSION = "(.+)"`
+    fn from_str(spec: &str) -> Result<Self> {
+        let (file_format, rest) = spec.split_once(':').ok_or_else(|| {
+            GitScribeError::Parse(format!(
+                "Invalid sync file '{spec}', expected FORMAT:PATH[:KEY]"
+            ))
+        })?;
+        let file_format = file_format.parse::<SupportedSyncFileFormat>()?;
+        if let SupportedSyncFileFormat::Regex = file_format {
+            return match rest.split_once(':') {
+                Some((file_path, pattern)) if !file_path.is_empty() && !pattern.is_empty() => {
+                    Ok(VersionSyncFile {
+                        file_format,
+                        file_path: file_path.to_string(),
+                        version_key: pattern.to_string(),
+                        additional_version_keys: vec![],
+                    })
+                }
+                _ => Err(GitScribeError::Parse(format!(
+                    "Invalid sync file '{spec}', expected Regex:PATH:PATTERN"
+                ))),
+            };
+        }
+        let (file_path, version_keys) = rest.rsplit_once(':').unwrap_or((rest, "version"));
+        let mut version_keys = version_keys.split(',').map(|key| key.trim().to_string());
+        let version_key = version_keys.next().unwrap_or_default();
+        let additional_version_keys = version_keys.collect::<Vec<String>>();
+        if file_path.is_empty()
+            || version_key.is_empty()
+            || additional_version_keys.iter().any(|key| key.is_empty())
+        {
+            return Err(GitScribeError::Parse(format!(
+                "Invalid sync file '{spec}', expected FORMAT:PATH[:KEY[,KEY...]]"
+            )));
+        }
+        Ok(VersionSyncFile {
+            file_format,
+            file_path: file_path.to_string(),
+            version_key,
+            additional_version_keys,
+        })
+    }
 }
 
-/// Function to sync the version number to the file which other package managers use to store the version number
+/// Computes the new contents of every version sync file without writing anything
 /// # Arguments
-/// * `version_files` - A vector of VersionSyncFile which contains the file format, file path and the version key
-/// * `version` - The version number to be updated in the file
+/// * `version_files` - The VersionSyncFiles which contain the file format, file path and the version key
+/// * `version` - The version number to be updated in the files
 /// # Returns
-/// * `Result<(), Error>` - A Result of unit type and Error
-pub fn sync_version_to_file(
-    version_files: Vec<VersionSyncFile>,
-    version: String,
-) -> Result<(), Error> {
+/// * `Result<IndexMap<String, String>>` - The new contents keyed by file path, or a sync file error if a file cannot be read, parsed or updated
+pub fn plan_version_sync(
+    version_files: &[VersionSyncFile],
+    version: &str,
+) -> Result<IndexMap<String, String>> {
+    let mut planned: IndexMap<String, String> = IndexMap::new();
+    extend_version_sync_plan(&mut planned, version_files, version)?;
+    Ok(planned)
+}
+
+/// Adds the new contents of the version sync files to a plan, building on the contents already planned
+/// so packages released together with different versions can share files like Cargo.lock
+/// # Errors
+/// * A sync file error if a file cannot be read, parsed or updated
+pub(crate) fn extend_version_sync_plan(
+    planned: &mut IndexMap<String, String>,
+    version_files: &[VersionSyncFile],
+    version: &str,
+) -> Result<()> {
     for version_file in version_files {
-        match version_file.file_format {
-            SupportedSyncFileFormat::Json => {
-                let file = std::fs::read_to_string(&version_file.file_path).unwrap();
-                let mut json: serde_json::Value = serde_json::from_str(&file).unwrap(); // Declare json as mutable
-                let json_obj = json.as_object_mut().unwrap(); // Get a mutable reference to the json object
-                json_obj.insert(
-                    version_file.version_key,
-                    serde_json::Value::String(version.clone()),
-                );
-                let json = serde_json::to_string_pretty(&json).unwrap();
-                std::fs::write(&version_file.file_path, json).unwrap();
+        // a file listed more than once builds on the contents planned so far
+        let read = |path: &str| match planned.get(path) {
+            Some(contents) => Ok(contents.clone()),
+            None => std::fs::read_to_string(path).map_err(|err| err.to_string()),
+        };
+        let files = match version_file.file_format {
+            SupportedSyncFileFormat::CargoWorkspace => {
+                plan_cargo_workspace_sync(&version_file.file_path, version, &read)
+                    .map_err(|reason| invalid(version_file, reason))?
             }
-            SupportedSyncFileFormat::CargoToml => {
-                let file = std::fs::read_to_string(&version_file.file_path).unwrap();
-                let mut toml = toml::from_str::<toml::Value>(&file).unwrap();
-                if let Some(package) = toml.as_table_mut().unwrap().get_mut("package") {
-                    package.as_table_mut().unwrap().insert(
-                        version_file.version_key,
-                        toml::Value::String(version.clone()),
-                    );
-                }
-                let toml = toml::to_string(&toml).unwrap();
-                std::fs::write(&version_file.file_path, toml).unwrap();
+            SupportedSyncFileFormat::Node => {
+                plan_node_sync(&version_file.file_path, version, &read)
+                    .map_err(|reason| invalid(version_file, reason))?
             }
-            SupportedSyncFileFormat::PoetryToml => {
-                let file = std::fs::read_to_string(&version_file.file_path).unwrap();
-                let mut toml = toml::from_str::<toml::Value>(&file).unwrap();
-                if let Some(tool) = toml.as_table_mut().unwrap().get_mut("tool") {
-                    if let Some(poetry) = tool.as_table_mut().unwrap().get_mut("poetry") {
-                        poetry.as_table_mut().unwrap().insert(
-                            version_file.version_key,
-                            toml::Value::String(version.clone()),
-                        );
-                    }
+            _ => {
+                let mut contents = read(&version_file.file_path)
+                    .map_err(|reason| invalid(version_file, reason))?;
+                for version_key in version_file.version_keys() {
+                    contents = set_version_key(version_file, &contents, version_key, version)?;
                 }
-                let toml = toml::to_string(&toml).unwrap();
-                std::fs::write(&version_file.file_path, toml).unwrap();
-            }
-            SupportedSyncFileFormat::Yaml => {
-                let file = std::fs::read_to_string(&version_file.file_path).unwrap();
-                let mut yaml = serde_yaml::from_str::<serde_yaml::Value>(&file).unwrap();
-                yaml.as_mapping_mut().unwrap().insert(
-                    serde_yaml::Value::String(version_file.version_key),
-                    serde_yaml::Value::String(version.clone()),
-                );
-                let yaml = serde_yaml::to_string(&yaml).unwrap();
-                std::fs::write(&version_file.file_path, yaml).unwrap();
+                vec![(version_file.file_path.clone(), contents)]
             }
+        };
+        planned.extend(files);
+    }
+    Ok(())
+}
+
+/// Sets the value of a single version key in the contents of a version sync file
+fn set_version_key(
+    version_file: &VersionSyncFile,
+    file: &str,
+    version_key: &str,
+    version: &str,
+) -> Result<String> {
+    match version_file.file_format {
+        SupportedSyncFileFormat::Json => {
+            set_json_string(file, &version_file.key_path(version_key)?, version)
+        }
+        SupportedSyncFileFormat::CargoToml => {
+            set_toml_version(file, &version_file.key_path(version_key)?, version).map_err(
+                |reason| match file.contains("[workspace]") {
+                    true => format!("{reason}, use the CargoWorkspace format for a workspace root"),
+                    false => reason,
+                },
+            )
+        }
+        SupportedSyncFileFormat::PoetryToml | SupportedSyncFileFormat::PyprojectToml => {
+            set_toml_version(file, &version_file.key_path(version_key)?, version)
         }
+        SupportedSyncFileFormat::Yaml => {
+            set_yaml_scalar(file, &version_file.key_path(version_key)?, version)
+        }
+        SupportedSyncFileFormat::Regex => set_regex_version(file, version_key, version),
+        SupportedSyncFileFormat::PlainText => Ok(set_plain_text_version(file, version)),
+        SupportedSyncFileFormat::CargoWorkspace | SupportedSyncFileFormat::Node => {
+            Err("the whole workspace is updated at once, not a single key".to_string())
+        }
+    }
+    .map_err(|reason| invalid(version_file, reason))
+}
 
-        println!("✅Updated version in {}", version_file.file_path.as_str());
+/// Sets the value at the key path of a TOML document, leaving the rest of the document byte for byte as it was
+/// Comments, key order, inline tables and the whitespace around the value are all kept
+/// # Arguments
+/// * `file` - The contents of the TOML file
+/// * `path` - The path from the root of the document e.g. `tool.poetry.version`
+/// * `version` - The new version
+/// # Returns
+/// * The new contents, or the reason the version could not be set
+fn set_toml_version(
+    file: &str,
+    path: &[PathSegment],
+    version: &str,
+) -> std::result::Result<String, String> {
+    let mut document = file
+        .parse::<toml_edit::Document>()
+        .map_err(|err| err.to_string())?;
+    let Some((last, parents)) = path.split_last() else {
+        return Err("an empty key path cannot be set".to_string());
+    };
+    let parent = parents
+        .iter()
+        .try_fold(document.as_item_mut(), |item, segment| match segment {
+            PathSegment::Key(key) => item.get_mut(key.as_str()),
+            PathSegment::Index(index) => item.get_mut(*index),
+        })
+        .ok_or_else(|| format!("missing [{}] table", display_key_path(parents)))?;
+    let value = match last {
+        PathSegment::Key(key) => {
+            let table = parent
+                .as_table_like_mut()
+                .ok_or_else(|| format!("[{}] is not a table", display_key_path(parents)))?;
+            // a misspelled key is reported as it is when the version is read, never added
+            table
+                .get_mut(key)
+                .ok_or_else(|| format!("missing key '{}'", display_key_path(path)))?
+                .as_value_mut()
+        }
+        PathSegment::Index(index) => parent.get_mut(*index).and_then(|item| item.as_value_mut()),
     }
+    .ok_or_else(|| format!("'{}' does not hold a value", display_key_path(path)))?;
+    let decor = value.decor().clone();
+    *value = toml_edit::Value::from(version);
+    *value.decor_mut() = decor;
+    Ok(document.to_string())
+}
 
-    Ok(())
+/// Compiles a Regex format pattern, checking it has a group to hold the version
+fn version_regex(pattern: &str) -> std::result::Result<Regex, String> {
+    let regex = Regex::new(pattern).map_err(|err| err.to_string())?;
+    if regex.captures_len() < 2 {
+        return Err(format!("the pattern '{pattern}' has no capture group"));
+    }
+    Ok(regex)
+}
+
+/// Replaces the version group of every match of the pattern, the `version` group if the pattern names one,
+/// otherwise the first group
+/// # Arguments
+/// * `file` - The contents of the file
+/// * `pattern` - The pattern matching the version e.g. `LABEL version="(.+)"`
+/// * `version` - The new version
+/// # Returns
+/// * The new contents, or the reason the version could not be set
+fn set_regex_version(
+    file: &str,
+    pattern: &str,
+    version: &str,
+) -> std::result::Result<String, String> {
+    let regex = version_regex(pattern)?;
+    let ranges = regex
+        .captures_iter(file)
+        .filter_map(|captures| captures.name("version").or_else(|| captures.get(1)))
+        .map(|group| group.range())
+        .collect::<Vec<std::ops::Range<usize>>>();
+    if ranges.is_empty() {
+        return Err(format!("nothing matches the pattern '{pattern}'"));
+    }
+    let mut contents = file.to_string();
+    for range in ranges.into_iter().rev() {
+        contents.replace_range(range, version);
+    }
+    Ok(contents)
+}
+
+/// Replaces the whole file with the version, keeping the whitespace around it
+fn set_plain_text_version(file: &str, version: &str) -> String {
+    let trimmed = file.trim();
+    if trimmed.is_empty() {
+        return format!("{version}\n");
+    }
+    let start = file.len() - file.trim_start().len();
+    format!(
+        "{}{version}{}",
+        &file[..start],
+        &file[start + trimmed.len()..]
+    )
+}
+
+/// Reads the version currently stored in a version sync file
+/// # Arguments
+/// * `version_file` - The VersionSyncFile to read
+/// # Returns
+/// * The version, or a sync file error if the file cannot be read or parsed, or the key is missing
+pub fn read_version(version_file: &VersionSyncFile) -> Result<String> {
+    let file = std::fs::read_to_string(&version_file.file_path)
+        .map_err(|err| invalid(version_file, err))?;
+    read_version_from_str(version_file, &file)
+}
+
+/// Reads the version stored under the version key of already loaded file contents
+/// # Arguments
+/// * `version_file` - The VersionSyncFile describing the format and the key
+/// * `file` - The contents of the file
+pub(crate) fn read_version_from_str(version_file: &VersionSyncFile, file: &str) -> Result<String> {
+    read_key_from_str(version_file, &version_file.version_key, file)
+}
+
+/// Reads the value stored under one of the version keys of already loaded file contents
+fn read_key_from_str(
+    version_file: &VersionSyncFile,
+    version_key: &str,
+    file: &str,
+) -> Result<String> {
+    // named by the full key path, as it is when the version is set
+    let missing_key = |path: &[PathSegment]| {
+        invalid(
+            version_file,
+            format!("missing key '{}'", display_key_path(path)),
+        )
+    };
+    match version_file.file_format {
+        SupportedSyncFileFormat::Regex => {
+            let regex =
+                version_regex(version_key).map_err(|reason| invalid(version_file, reason))?;
+            regex
+                .captures(file)
+                .and_then(|captures| captures.name("version").or_else(|| captures.get(1)))
+                .map(|group| group.as_str().to_string())
+                .ok_or_else(|| {
+                    invalid(
+                        version_file,
+                        format!("nothing matches the pattern '{version_key}'"),
+                    )
+                })
+        }
+        SupportedSyncFileFormat::CargoWorkspace => {
+            read_workspace_version(file).map_err(|reason| invalid(version_file, reason))
+        }
+        SupportedSyncFileFormat::Node => {
+            read_node_version(file).map_err(|reason| invalid(version_file, reason))
+        }
+        SupportedSyncFileFormat::PlainText => match file.trim() {
+            "" => Err(invalid(version_file, "the file is empty")),
+            version => Ok(version.to_string()),
+        },
+        SupportedSyncFileFormat::Json => {
+            let path = version_file.key_path(version_key)?;
+            let json: serde_json::Value =
+                serde_json::from_str(file).map_err(|err| invalid(version_file, err))?;
+            path.iter()
+                .try_fold(&json, |value, segment| match segment {
+                    PathSegment::Key(key) => value.get(key),
+                    PathSegment::Index(index) => value.get(index),
+                })
+                .and_then(|version| version.as_str())
+                .map(|version| version.to_string())
+                .ok_or_else(|| missing_key(&path))
+        }
+        SupportedSyncFileFormat::CargoToml
+        | SupportedSyncFileFormat::PoetryToml
+        | SupportedSyncFileFormat::PyprojectToml => {
+            let path = version_file.key_path(version_key)?;
+            let toml =
+                toml::from_str::<toml::Value>(file).map_err(|err| invalid(version_file, err))?;
+            path.iter()
+                .try_fold(&toml, |value, segment| match segment {
+                    PathSegment::Key(key) => value.get(key),
+                    PathSegment::Index(index) => value.get(index),
+                })
+                .and_then(|version| version.as_str())
+                .map(|version| version.to_string())
+                .ok_or_else(|| missing_key(&path))
+        }
+        SupportedSyncFileFormat::Yaml => {
+            let path = version_file.key_path(version_key)?;
+            let yaml = serde_yaml::from_str::<serde_yaml::Value>(file)
+                .map_err(|err| invalid(version_file, err))?;
+            path.iter()
+                .try_fold(&yaml, |value, segment| match segment {
+                    PathSegment::Key(key) => value.get(key),
+                    PathSegment::Index(index) => value.get(index),
+                })
+                .and_then(|version| match version {
+                    serde_yaml::Value::String(version) => Some(version.clone()),
+                    serde_yaml::Value::Number(version) => Some(version.to_string()),
+                    _ => None,
+                })
+                .ok_or_else(|| missing_key(&path))
+        }
+    }
+}
+
+/// The version found under one key of a version sync file, or the reason it could not be read
+#[derive(Debug)]
+pub struct SyncFileCheck {
+    pub file_path: String,
+    pub file_format: SupportedSyncFileFormat,
+    pub version_key: String,
+    pub found: Result<String>,
+}
+impl SyncFileCheck {
+    /// Returns true if the key could be read and holds the expected version
+    pub fn is_in_sync(&self, version: &str) -> bool {
+        matches!(&self.found, Ok(found) if found == version)
+    }
+}
+
+/// Reads every key of every version sync file without changing anything, to compare against the configured version
+///
+/// The `CargoWorkspace` and `Node` formats report every file they update, see `check_workspace`
+/// # Arguments
+/// * `version_files` - The VersionSyncFiles to read
+/// # Returns
+/// * One check per version key, in the order the files and keys are configured
+pub fn check_version_sync(version_files: &[VersionSyncFile]) -> Vec<SyncFileCheck> {
+    let mut checks = vec![];
+    for version_file in version_files {
+        if matches!(
+            version_file.file_format,
+            SupportedSyncFileFormat::CargoWorkspace | SupportedSyncFileFormat::Node
+        ) {
+            checks.extend(check_workspace(version_file));
+            continue;
+        }
+        let file = std::fs::read_to_string(&version_file.file_path);
+        for version_key in version_file.version_keys() {
+            let found = match &file {
+                Ok(file) => read_key_from_str(version_file, version_key, file),
+                Err(err) => Err(invalid(version_file, err)),
+            };
+            checks.push(SyncFileCheck {
+                file_path: version_file.file_path.clone(),
+                file_format: version_file.file_format.clone(),
+                version_key: version_key.clone(),
+                found,
+            });
+        }
+    }
+    checks
+}
+
+/// Checks every file a workspace format updates, with each version the file holds
+///
+/// Files without a version of their own, like a pnpm-lock.yaml or a member that only depends on other members,
+/// are compared against the root's version and reported under `dependencies`, out of sync if syncing would change them.
+/// Cargo members versioned on their own are not released with the root, they are only reported when their dependencies lag
+fn check_workspace(version_file: &VersionSyncFile) -> Vec<SyncFileCheck> {
+    let read = |path: &str| std::fs::read_to_string(path).map_err(|err| err.to_string());
+    let check = |file_path: &str, version_key: &str, found: Result<String>| SyncFileCheck {
+        file_path: file_path.to_string(),
+        file_format: version_file.file_format.clone(),
+        version_key: version_key.to_string(),
+        found,
+    };
+    let cargo = matches!(
+        version_file.file_format,
+        SupportedSyncFileFormat::CargoWorkspace
+    );
+    let workspace = read(&version_file.file_path).and_then(|root| {
+        let root_version = match cargo {
+            true => read_workspace_version(&root)?,
+            false => read_node_version(&root)?,
+        };
+        // planned with the root's own version, so any file that would change lags behind the root
+        let (planned, versions) = match cargo {
+            true => (
+                plan_cargo_workspace_sync(&version_file.file_path, &root_version, &read)?,
+                read_cargo_workspace_versions(&version_file.file_path, &read)?,
+            ),
+            false => (
+                plan_node_sync(&version_file.file_path, &root_version, &read)?,
+                read_node_versions(&version_file.file_path, &read)?,
+            ),
+        };
+        Ok((root_version, planned, versions))
+    });
+    let (root_version, planned, versions) = match workspace {
+        Ok(workspace) => workspace,
+        Err(reason) => {
+            return vec![check(
+                &version_file.file_path,
+                &version_file.version_key,
+                Err(invalid(version_file, reason)),
+            )]
+        }
+    };
+
+    let mut checks = vec![];
+    for (file_path, contents) in planned {
+        let held = versions
+            .iter()
+            .filter(|(path, _, _)| *path == file_path)
+            .collect::<Vec<_>>();
+        for (_, version_key, version) in &held {
+            checks.push(check(&file_path, version_key, Ok(version.clone())));
+        }
+        let current = read(&file_path);
+        let stale = current.as_ref().is_ok_and(|current| *current != contents);
+        let independent = cargo
+            && held.is_empty()
+            && current.is_ok_and(|current| read_package_version(&current).is_some());
+        if independent && !stale {
+            continue;
+        }
+        if held.is_empty() || (stale && held.iter().all(|(_, _, version)| *version == root_version))
+        {
+            let found = match stale {
+                true => Err(GitScribeError::sync_file(
+                    &file_path,
+                    format!("its dependencies are behind version {root_version}"),
+                )),
+                false => Ok(root_version.clone()),
+            };
+            checks.push(check(&file_path, "dependencies", found));
+        }
+    }
+    checks
+}
+
+/// Syncs the version number to the files which other package managers use to store the version number
+///
+/// Every file is computed first with `plan_version_sync`, so nothing is written if any file fails
+/// # Arguments
+/// * `version_files` - The VersionSyncFiles which contain the file format, file path and the version key
+/// * `version` - The version number to be updated in the files
+/// # Returns
+/// * `Result<Vec<String>>` - The paths of the files that were written
+/// # Examples
+/// ```no_run
+/// use gitscribe::version_file_sync::{sync_version_to_file, SupportedSyncFileFormat, VersionSyncFile};
+/// let updated = sync_version_to_file(
+///     &[VersionSyncFile {
+///         file_format: SupportedSyncFileFormat::CargoToml,
+///         file_path: "./Cargo.toml".to_string(),
+///         version_key: "version".to_string(),
+///         additional_version_keys: vec![],
+///     }],
+///     "1.2.3",
+/// )
+/// .unwrap();
+/// ```
+pub fn sync_version_to_file(
+    version_files: &[VersionSyncFile],
+    version: &str,
+) -> Result<Vec<String>> {
+    let mut transaction = ReleaseTransaction::new();
+    let planned = plan_version_sync(version_files, version)?;
+    for (file_path, contents) in &planned {
+        transaction.stage(file_path, contents.clone());
+    }
+    transaction.apply()?;
+    Ok(planned.into_keys().collect())
+}
+
+/// Builds the error returned when a version sync file cannot be read, parsed or updated
+fn invalid(version_file: &VersionSyncFile, reason: impl std::fmt::Display) -> GitScribeError {
+    GitScribeError::sync_file(
+        &version_file.file_path,
+        format!("{} ({})", reason, version_file.file_format),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sync_file(dir: &tempfile::TempDir, name: &str, contents: &str) -> String {
+        let path = dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_plan_version_sync_does_not_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = sync_file(
+            &dir,
+            "package.json",
+            r#"{"name": "app", "version": "0.0.1"}"#,
+        );
+        let planned = plan_version_sync(
+            &[VersionSyncFile {
+                file_format: SupportedSyncFileFormat::Json,
+                file_path: file_path.clone(),
+                version_key: "version".to_string(),
+                additional_version_keys: vec![],
+            }],
+            "0.0.2",
+        )
+        .unwrap();
+
+        assert_eq!(
+            planned[&file_path],
+            r#"{"name": "app", "version": "0.0.2"}"#
+        );
+        assert!(std::fs::read_to_string(&file_path)
+            .unwrap()
+            .contains("0.0.1"));
+    }
+
+    #[test]
+    fn test_cargo_toml_sync_only_changes_the_version() {
+        let original = r#"# the app crate
+[package]
+name = "app"
+version = "0.0.1" # bumped by gitscribe
+edition = "2021"
+
+[dependencies]
+serde = { version = "1.0", features = ["derive"] }
+regex = "1.10.3"
+"#;
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = sync_file(&dir, "Cargo.toml", original);
+        let planned = plan_version_sync(
+            &[VersionSyncFile {
+                file_format: SupportedSyncFileFormat::CargoToml,
+                file_path: file_path.clone(),
+                version_key: "version".to_string(),
+                additional_version_keys: vec![],
+            }],
+            "0.0.2",
+        )
+        .unwrap();
+
+        assert_eq!(
+            planned[&file_path],
+            original.replace(r#"version = "0.0.1""#, r#"version = "0.0.2""#)
+        );
+    }
+
+    #[test]
+    fn test_poetry_toml_sync_only_changes_the_version() {
+        let original = r#"[build-system]
+requires = ["poetry-core"]
+
+[tool.poetry]
+name = "app"   # aligned
+version   =   '0.0.1'
+authors = [
+    "John Doe <john@example.com>",
+]
+"#;
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = sync_file(&dir, "pyproject.toml", original);
+        let planned = plan_version_sync(
+            &[VersionSyncFile {
+                file_format: SupportedSyncFileFormat::PoetryToml,
+                file_path: file_path.clone(),
+                version_key: "version".to_string(),
+                additional_version_keys: vec![],
+            }],
+            "0.0.2",
+        )
+        .unwrap();
+
+        assert_eq!(
+            planned[&file_path],
+            original.replace("'0.0.1'", r#""0.0.2""#)
+        );
+    }
+
+    #[test]
+    fn test_plan_version_sync_reports_missing_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = "[tool.poetry]\nname = \"app\"\nversion = \"0.0.1\"\n";
+        let version_file = VersionSyncFile {
+            file_format: SupportedSyncFileFormat::PoetryToml,
+            file_path: sync_file(&dir, "pyproject.toml", original),
+            version_key: "vresion".to_string(),
+            additional_version_keys: vec![],
+        };
+        let planned = plan_version_sync(std::slice::from_ref(&version_file), "0.0.2").unwrap_err();
+        let read = read_version(&version_file).unwrap_err();
+
+        assert!(matches!(planned, GitScribeError::SyncFile { .. }));
+        assert_eq!(planned.to_string(), read.to_string());
+        assert_eq!(
+            std::fs::read_to_string(&version_file.file_path).unwrap(),
+            original
+        );
+    }
+
+    #[test]
+    fn test_plan_version_sync_reports_missing_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = sync_file(&dir, "Cargo.toml", "[workspace]\nmembers = []\n");
+        let result = plan_version_sync(
+            &[VersionSyncFile {
+                file_format: SupportedSyncFileFormat::CargoToml,
+                file_path,
+                version_key: "version".to_string(),
+                additional_version_keys: vec![],
+            }],
+            "0.0.2",
+        );
+
+        assert!(matches!(result, Err(GitScribeError::SyncFile { .. })));
+    }
+
+    #[test]
+    fn test_plan_version_sync_follows_key_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let cargo_path = sync_file(
+            &dir,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"app\"]\n\n[workspace.package]\nversion = \"0.0.1\"\n",
+        );
+        let pyproject_path = sync_file(
+            &dir,
+            "pyproject.toml",
+            "[project]\nname = \"app\"\nversion = \"0.0.1\"\n",
+        );
+        let chart_path = sync_file(
+            &dir,
+            "Chart.yaml",
+            "name: app\nversion: 0.0.1\nappVersion: \"0.0.1\"\n",
+        );
+        let version_files = [
+            VersionSyncFile {
+                file_format: SupportedSyncFileFormat::CargoToml,
+                file_path: cargo_path.clone(),
+                version_key: "workspace.package.version".to_string(),
+                additional_version_keys: vec![],
+            },
+            VersionSyncFile {
+                file_format: SupportedSyncFileFormat::PoetryToml,
+                file_path: pyproject_path.clone(),
+                version_key: "project.version".to_string(),
+                additional_version_keys: vec![],
+            },
+            format!("Yaml:{chart_path}:version,appVersion")
+                .parse()
+                .unwrap(),
+        ];
+        let planned = plan_version_sync(&version_files, "0.0.2").unwrap();
+
+        assert_eq!(
+            planned[&cargo_path],
+            "[workspace]\nmembers = [\"app\"]\n\n[workspace.package]\nversion = \"0.0.2\"\n"
+        );
+        assert_eq!(
+            planned[&pyproject_path],
+            "[project]\nname = \"app\"\nversion = \"0.0.2\"\n"
+        );
+        assert_eq!(
+            planned[&chart_path],
+            "name: app\nversion: 0.0.2\nappVersion: \"0.0.2\"\n"
+        );
+        for version_file in &version_files {
+            assert_eq!(read_version(version_file).unwrap(), "0.0.1");
+        }
+    }
+
+    #[test]
+    fn test_regex_and_plain_text_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let python_path = sync_file(
+            &dir,
+            "__init__.py",
+            "__version__ = \"0.0.1\"\nother = \"0.0.1\"\n",
+        );
+        let dockerfile_path = sync_file(
+            &dir,
+            "Dockerfile",
+            "FROM scratch\nLABEL version=\"0.0.1\"\nLABEL version=\"0.0.1\"\n",
+        );
+        let plain_path = sync_file(&dir, "VERSION", "0.0.1\n");
+        let version_files = [
+            format!(r#"Regex:{python_path}:__version__ = "(.+)""#)
+                .parse::<VersionSyncFile>()
+                .unwrap(),
+            format!(r#"Regex:{dockerfile_path}:version="(?<version>[^"]+)""#)
+                .parse::<VersionSyncFile>()
+                .unwrap(),
+            format!("PlainText:{plain_path}").parse().unwrap(),
+        ];
+        for version_file in &version_files {
+            assert_eq!(read_version(version_file).unwrap(), "0.0.1");
+        }
+
+        let planned = plan_version_sync(&version_files, "0.0.2").unwrap();
+        assert_eq!(
+            planned[&python_path],
+            "__version__ = \"0.0.2\"\nother = \"0.0.1\"\n"
+        );
+        assert_eq!(
+            planned[&dockerfile_path],
+            "FROM scratch\nLABEL version=\"0.0.2\"\nLABEL version=\"0.0.2\"\n"
+        );
+        assert_eq!(planned[&plain_path], "0.0.2\n");
+
+        let no_group = VersionSyncFile {
+            file_format: SupportedSyncFileFormat::Regex,
+            file_path: python_path,
+            version_key: "__version__".to_string(),
+            additional_version_keys: vec![],
+        };
+        assert!(plan_version_sync(&[no_group], "0.0.2").is_err());
+    }
+
+    #[test]
+    fn test_check_version_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let chart_path = sync_file(&dir, "Chart.yaml", "version: 0.0.2\nappVersion: 0.0.1\n");
+        let version_files = [
+            format!("Yaml:{chart_path}:version,appVersion,kubeVersion")
+                .parse::<VersionSyncFile>()
+                .unwrap(),
+            format!("Json:{}/package.json", dir.path().display())
+                .parse()
+                .unwrap(),
+        ];
+        let checks = check_version_sync(&version_files);
+
+        let in_sync = checks
+            .iter()
+            .map(|check| (check.version_key.as_str(), check.is_in_sync("0.0.2")))
+            .collect::<Vec<(&str, bool)>>();
+        assert_eq!(
+            in_sync,
+            vec![
+                ("version", true),
+                ("appVersion", false),
+                ("kubeVersion", false),
+                ("version", false)
+            ]
+        );
+        assert_eq!(checks[1].found.as_ref().unwrap(), "0.0.1");
+        assert!(matches!(
+            checks[2].found,
+            Err(GitScribeError::SyncFile { .. })
+        ));
+        assert!(matches!(
+            checks[3].found,
+            Err(GitScribeError::SyncFile { .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_workspace_reports_every_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("crates/core")).unwrap();
+        std::fs::create_dir_all(dir.path().join("crates/cli")).unwrap();
+        std::fs::create_dir_all(dir.path().join("crates/api")).unwrap();
+        let root = sync_file(
+            &dir,
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\"]\n\n[workspace.package]\nversion = \"1.1.0\"\n",
+        );
+        // versioned on its own, so neither it nor the requirements on it are drift
+        sync_file(
+            &dir,
+            "crates/core/Cargo.toml",
+            "[package]\nname = \"core\"\nversion = \"1.0.0\"\n",
+        );
+        sync_file(
+            &dir,
+            "crates/cli/Cargo.toml",
+            "[package]\nname = \"cli\"\nversion.workspace = true\n\n[dependencies]\napi = { path = \"../api\", version = \"1.0.0\" }\ncore = { path = \"../core\", version = \"1.0.0\" }\n",
+        );
+        sync_file(
+            &dir,
+            "crates/api/Cargo.toml",
+            "[package]\nname = \"api\"\nversion.workspace = true\n",
+        );
+        sync_file(
+            &dir,
+            "Cargo.lock",
+            "version = 3\n\n[[package]]\nname = \"api\"\nversion = \"1.0.0\"\n\n[[package]]\nname = \"cli\"\nversion = \"1.1.0\"\n\n[[package]]\nname = \"core\"\nversion = \"1.0.0\"\n",
+        );
+        let version_files = [format!("CargoWorkspace:{root}")
+            .parse::<VersionSyncFile>()
+            .unwrap()];
+        let checks = check_version_sync(&version_files)
+            .into_iter()
+            .map(|check| {
+                let file_path = std::path::Path::new(&check.file_path)
+                    .strip_prefix(dir.path())
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                (
+                    file_path,
+                    check.version_key.clone(),
+                    check.is_in_sync("1.1.0"),
+                )
+            })
+            .collect::<Vec<(String, String, bool)>>();
+        let expected = [
+            ("Cargo.toml", "workspace.package.version", true),
+            ("crates/api/Cargo.toml", "package.version.workspace", true),
+            ("crates/cli/Cargo.toml", "package.version.workspace", true),
+            ("crates/cli/Cargo.toml", "dependencies", false),
+            ("Cargo.lock", "package.\"api\".version", false),
+            ("Cargo.lock", "package.\"cli\".version", true),
+        ];
+        assert_eq!(
+            checks,
+            expected
+                .iter()
+                .map(|(path, key, in_sync)| (path.to_string(), key.to_string(), *in_sync))
+                .collect::<Vec<_>>()
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("packages/app")).unwrap();
+        let root = sync_file(
+            &dir,
+            "package.json",
+            r#"{"name": "root", "version": "2.0.0", "workspaces": ["packages/*"]}"#,
+        );
+        sync_file(
+            &dir,
+            "packages/app/package.json",
+            r#"{"name": "app", "version": "1.9.0"}"#,
+        );
+        sync_file(
+            &dir,
+            "package-lock.json",
+            r#"{"version": "2.0.0", "packages": {"": {"version": "2.0.0"}, "packages/app": {"version": "1.9.0"}}}"#,
+        );
+        let version_files = [format!("Node:{root}").parse::<VersionSyncFile>().unwrap()];
+        let checks = check_version_sync(&version_files);
+        let drifted = checks
+            .iter()
+            .filter(|check| !check.is_in_sync("2.0.0"))
+            .map(|check| {
+                (
+                    check.version_key.as_str(),
+                    check.found.as_ref().unwrap().as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(checks.len(), 5);
+        assert_eq!(
+            drifted,
+            vec![
+                ("version", "1.9.0"),
+                ("packages.\"packages/app\".version", "1.9.0")
+            ]
+        );
+    }
 }
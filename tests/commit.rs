@@ -0,0 +1,83 @@
+//! The release commit holds only the files the release wrote
+mod common;
+
+use common::{config, git, gitscribe, init_repo};
+use serde_json::json;
+
+#[test]
+fn test_release_commit_leaves_other_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let work = dir.path();
+    init_repo(work, &config(json!({})));
+    std::fs::write(work.join(".gitignore"), "build.log\n").unwrap();
+    git(work, &["add", "."]);
+    git(work, &["commit", "-q", "-m", "feat: first feature"]);
+    // a build artifact written while releasing stays out of the release commit
+    std::fs::write(work.join("build.log"), "output").unwrap();
+
+    let output = gitscribe(work, &["minor"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        git(work, &["show", "--name-only", "--format=", "HEAD"]),
+        "gitscribe.json"
+    );
+    assert_eq!(
+        git(work, &["status", "--porcelain", "--ignored"]),
+        "!! build.log"
+    );
+}
+
+#[test]
+fn test_release_commit_leaves_files_created_while_releasing() {
+    let dir = tempfile::tempdir().unwrap();
+    let work = dir.path();
+    // written after the clean working tree check and not ignored
+    init_repo(
+        work,
+        &config(json!({"hooks": {"post_sync": ["echo untracked > notes.txt"]}})),
+    );
+    git(work, &["add", "."]);
+    git(work, &["commit", "-q", "-m", "feat: first feature"]);
+
+    let output = gitscribe(work, &["minor"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        git(work, &["show", "--name-only", "--format=", "HEAD"]),
+        "gitscribe.json"
+    );
+    assert_eq!(
+        git(work, &["status", "--porcelain", "--untracked-files=all"]),
+        "?? notes.txt"
+    );
+}
+
+#[test]
+fn test_release_committer_leaves_the_author() {
+    let dir = tempfile::tempdir().unwrap();
+    let work = dir.path();
+    init_repo(
+        work,
+        &config(json!({"commit_committer": "CI <ci@example.com>"})),
+    );
+    git(work, &["add", "."]);
+    git(work, &["commit", "-q", "-m", "feat: first feature"]);
+
+    let output = gitscribe(work, &["minor"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        git(work, &["log", "-1", "--format=%an <%ae>, %cn <%ce>"]),
+        "Dev <dev@example.com>, CI <ci@example.com>"
+    );
+}
@@ -0,0 +1,47 @@
+//! Helpers to run git and gitscribe in a temporary repository
+use std::path::Path;
+use std::process::{Command, Output};
+
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+pub fn gitscribe(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_gitscribe"))
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// The config of a test repo, the shared base with the fields a test exercises replaced
+/// # Arguments
+/// * `overrides` - A JSON object of the top level fields to replace e.g. `json!({"version": "1.4.0"})`
+pub fn config(overrides: serde_json::Value) -> String {
+    let mut config = serde_json::json!({
+        "version": "1.0.0", "commands_that_release": [], "branch_for_release": false,
+        "changelog_output_selections": [], "project_repo": null, "version_sync_files": null
+    });
+    if let (Some(config), Some(overrides)) = (config.as_object_mut(), overrides.as_object()) {
+        config.extend(overrides.clone());
+    }
+    serde_json::to_string_pretty(&config).unwrap()
+}
+
+/// Initializes a repo on `main` with a committer and writes its gitscribe.json, nothing is committed
+pub fn init_repo(dir: &Path, config_json: &str) {
+    git(dir, &["init", "-q", "-b", "main"]);
+    git(dir, &["config", "user.email", "dev@example.com"]);
+    git(dir, &["config", "user.name", "Dev"]);
+    std::fs::write(dir.join("gitscribe.json"), config_json).unwrap();
+}
@@ -0,0 +1,115 @@
+//! Release hooks, the tracked files they change are committed and a failing hook undoes the release
+mod common;
+
+use common::{config, git, gitscribe, init_repo};
+use serde_json::json;
+
+/// A repo on `main` with one feature and a lockfile committed and the hooks in its config
+fn setup(hooks: serde_json::Value) -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let work = dir.path();
+    init_repo(
+        work,
+        &config(json!({
+            "changelog_output_selections": [{"template_option": "Markdown", "output_filepath": "CHANGELOG.md"}],
+            "packages": [{"name": "api", "version": "0.1.0", "paths": ["api"]}],
+            "hooks": hooks
+        })),
+    );
+    std::fs::write(work.join("app.lock"), "version = 1.0.0\n").unwrap();
+    git(work, &["add", "."]);
+    git(work, &["commit", "-q", "-m", "feat: first feature"]);
+    dir
+}
+
+#[test]
+fn test_hook_changes_are_committed() {
+    let dir = setup(json!({
+        "post_sync": [
+            "echo \"version = $GITSCRIBE_VERSION\" > app.lock",
+            "echo \"$GITSCRIBE_PREVIOUS_VERSION -> $GITSCRIBE_VERSION\" > built.txt"
+        ],
+        "post_commit": ["printf '%s' \"$GITSCRIBE_CHANGED_FILES\" > .git/changed"],
+        "post_tag": ["printf '%s' \"$GITSCRIBE_HOOK $GITSCRIBE_NOTES_FILE\" > .git/hook"]
+    }));
+    let work = dir.path();
+    let output = gitscribe(work, &["minor"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    // the lockfile is tracked, the untracked build output stays out of the release commit
+    assert_eq!(
+        git(work, &["show", "--name-only", "--format=", "HEAD"]),
+        "CHANGELOG.md\napp.lock\ngitscribe.json"
+    );
+    assert_eq!(git(work, &["show", "HEAD:app.lock"]), "version = 1.1.0");
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("built.txt is not tracked, it has been left out of the release commit."));
+    assert_eq!(
+        std::fs::read_to_string(work.join(".git/changed")).unwrap(),
+        "gitscribe.json\nCHANGELOG.md\napp.lock"
+    );
+    assert_eq!(
+        std::fs::read_to_string(work.join(".git/hook")).unwrap(),
+        "post-tag CHANGELOG.md"
+    );
+    assert_eq!(git(work, &["status", "--porcelain"]), "?? built.txt");
+}
+
+#[test]
+fn test_failing_hook_undoes_the_release() {
+    let dir = setup(json!({
+        "post_sync": ["echo built > built.txt", "echo changed >> gitscribe.json"],
+        "pre_commit": ["exit 1"]
+    }));
+    let work = dir.path();
+    let head = git(work, &["rev-parse", "HEAD"]);
+    let output = gitscribe(work, &["minor"]);
+    assert_eq!(output.status.code(), Some(11));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("`exit 1` exited with exit status: 1 in the pre-commit hook"));
+
+    assert_eq!(git(work, &["rev-parse", "HEAD"]), head);
+    assert_eq!(
+        git(work, &["status", "--porcelain", "--untracked-files=all"]),
+        ""
+    );
+}
+
+#[test]
+fn test_failing_post_tag_hook_removes_the_release_commit_and_tags() {
+    let dir = setup(json!({"post_tag": ["test \"$GITSCRIBE_PACKAGE\" != api"]}));
+    let work = dir.path();
+    let head = git(work, &["rev-parse", "HEAD"]);
+    let output = gitscribe(work, &["minor", "--package", "api"]);
+    assert_eq!(output.status.code(), Some(11));
+
+    assert_eq!(git(work, &["rev-parse", "HEAD"]), head);
+    assert_eq!(git(work, &["tag", "--list"]), "");
+    assert_eq!(
+        git(work, &["status", "--porcelain", "--untracked-files=all"]),
+        ""
+    );
+}
+
+#[test]
+fn test_failing_tag_removes_the_release_commit_without_hooks() {
+    let dir = setup(json!({}));
+    let work = dir.path();
+    git(work, &["checkout", "-q", "-b", "release/1.X.X"]);
+    // the hotfix tag is already taken, so tagging the release commit fails
+    git(work, &["tag", "v1.0.1"]);
+    let head = git(work, &["rev-parse", "HEAD"]);
+    let output = gitscribe(work, &["patch"]);
+    assert_eq!(output.status.code(), Some(3));
+
+    assert_eq!(git(work, &["rev-parse", "HEAD"]), head);
+    assert_eq!(git(work, &["tag", "--list"]), "v1.0.1");
+    assert_eq!(
+        git(work, &["status", "--porcelain", "--untracked-files=all"]),
+        ""
+    );
+}
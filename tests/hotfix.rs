@@ -0,0 +1,116 @@
+//! Hotfix releases tagged on a release branch after later commits on main, merged back or not
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+
+use common::{config, git, gitscribe, init_repo};
+use serde_json::json;
+
+/// Commits everything with the author and committer date set, so the history spans months
+fn commit_at(work: &Path, message: &str, date: &str) {
+    git(work, &["add", "."]);
+    let output = Command::new("git")
+        .current_dir(work)
+        .args(["commit", "-q", "--allow-empty", "-m", message])
+        .env("GIT_AUTHOR_DATE", date)
+        .env("GIT_COMMITTER_DATE", date)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+/// A config at the version writing the Markdown changelog
+fn changelog_config(version: &str) -> String {
+    config(json!({
+        "version": version,
+        "changelog_output_selections": [{"template_option": "Markdown", "output_filepath": "CHANGELOG.md"}]
+    }))
+}
+
+/// Writes the config at the version and commits it as the release of the version
+fn release_at(work: &Path, version: &str, date: &str) {
+    std::fs::write(work.join("gitscribe.json"), changelog_config(version)).unwrap();
+    commit_at(
+        work,
+        &format!("chore: 📝 update changelog and bump version to {version}"),
+        date,
+    );
+}
+
+/// Releases 1.4.0 in January and 1.5.0 in March on main, then adds feature x on main in April
+/// and hotfixes 1.4.3 on release/1.4.X in May
+fn setup() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let work = dir.path();
+    init_repo(work, &changelog_config("1.3.0"));
+    commit_at(work, "feat: first feature", "2024-01-10T12:00:00+00:00");
+    release_at(work, "1.4.0", "2024-01-15T12:00:00+00:00");
+    git(work, &["branch", "release/1.4.X"]);
+    commit_at(work, "feat: march feature", "2024-03-01T12:00:00+00:00");
+    release_at(work, "1.5.0", "2024-03-10T12:00:00+00:00");
+    commit_at(work, "feat: feature x", "2024-04-01T12:00:00+00:00");
+
+    git(work, &["checkout", "-q", "release/1.4.X"]);
+    commit_at(work, "fix: hotfix bug", "2024-05-01T12:00:00+00:00");
+    release_at(work, "1.4.3", "2024-05-10T12:00:00+00:00");
+    git(work, &["tag", "v1.4.3"]);
+    git(work, &["checkout", "-q", "main"]);
+    dir
+}
+
+/// The lines of the changelog section of a version
+fn section(changelog: &str, version: &str) -> String {
+    changelog
+        .split("\n## ")
+        .find(|section| section.starts_with(&format!("{version}\n")))
+        .unwrap_or_else(|| panic!("no {version} section in {changelog}"))
+        .to_string()
+}
+
+fn release_changelog(work: &Path) -> String {
+    let output = gitscribe(work, &["minor"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    std::fs::read_to_string(work.join("CHANGELOG.md")).unwrap()
+}
+
+#[test]
+fn test_merged_hotfix_only_lists_its_own_commits() {
+    let dir = setup();
+    let work = dir.path();
+    git(
+        work,
+        &["merge", "-q", "-s", "ours", "--no-edit", "release/1.4.X"],
+    );
+
+    let changelog = release_changelog(work);
+    let hotfix = section(&changelog, "1.4.3");
+    assert!(hotfix.contains("fix: hotfix bug"));
+    assert!(!hotfix.contains("feature x"), "{hotfix}");
+    assert!(section(&changelog, "1.6.0").contains("feat: feature x"));
+    assert!(section(&changelog, "1.5.0").contains("feat: march feature"));
+    assert!(section(&changelog, "1.4.0").contains("feat: first feature"));
+    assert!(!section(&changelog, "1.6.0").contains("hotfix bug"));
+}
+
+#[test]
+fn test_unmerged_hotfix_only_lists_its_own_commits() {
+    let dir = setup();
+    let work = dir.path();
+
+    let changelog = release_changelog(work);
+    let hotfix = section(&changelog, "1.4.3");
+    assert!(hotfix.contains("fix: hotfix bug"));
+    assert!(!hotfix.contains("feature x"), "{hotfix}");
+    assert!(section(&changelog, "1.6.0").contains("feat: feature x"));
+    assert!(!section(&changelog, "1.6.0").contains("hotfix bug"));
+    assert!(section(&changelog, "1.5.0").contains("feat: march feature"));
+}
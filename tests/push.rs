@@ -0,0 +1,102 @@
+//! Releases pushed with `--push` to a local bare repository acting as the remote
+mod common;
+
+use common::{config, git, gitscribe, init_repo};
+use serde_json::json;
+
+/// A work repo on `main` tracking a bare remote, with one commit pushed
+fn setup(config: &str) -> (tempfile::TempDir, std::path::PathBuf, std::path::PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let remote = dir.path().join("remote.git");
+    let work = dir.path().join("work");
+    std::fs::create_dir_all(&work).unwrap();
+    git(
+        dir.path(),
+        &["init", "-q", "--bare", "-b", "main", "remote.git"],
+    );
+    init_repo(&work, config);
+    git(&work, &["add", "."]);
+    git(&work, &["commit", "-q", "-m", "feat: first feature"]);
+    git(
+        &work,
+        &["remote", "add", "origin", remote.to_str().unwrap()],
+    );
+    git(&work, &["push", "-q", "-u", "origin", "main"]);
+    (dir, remote, work)
+}
+
+/// The packages of the config, releasing api on its own
+fn packages() -> serde_json::Value {
+    json!([{"name": "api", "version": "0.1.0", "paths": ["api"]}])
+}
+
+#[test]
+fn test_push_release_commit_and_tag() {
+    let (_dir, remote, work) = setup(&config(json!({"packages": packages()})));
+    let output = gitscribe(&work, &["minor", "--package", "api", "--push"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    assert_eq!(
+        git(&remote, &["log", "-1", "--format=%s", "main"]),
+        "chore: 📝 update changelog and bump version to api@0.2.0"
+    );
+    assert_eq!(git(&remote, &["tag", "--list"]), "api@0.2.0");
+}
+
+#[test]
+fn test_push_configured_remote() {
+    let (_dir, remote, work) = setup(&config(json!({
+        "packages": packages(), "push": true, "push_remote": "upstream"
+    })));
+    git(
+        &work,
+        &["remote", "add", "upstream", remote.to_str().unwrap()],
+    );
+    git(&work, &["remote", "set-url", "origin", "/nonexistent"]);
+    git(
+        &work,
+        &["commit", "-q", "--allow-empty", "-m", "fix: second"],
+    );
+
+    let output = gitscribe(&work, &["patch"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+    assert_eq!(
+        git(&remote, &["log", "-1", "--format=%s", "main"]),
+        "chore: 📝 update changelog and bump version to 1.0.1"
+    );
+}
+
+#[test]
+fn test_rejected_push_is_reported_and_atomic() {
+    let (dir, remote, work) = setup(&config(json!({"packages": packages()})));
+    // someone else pushed first, so pushing main is rejected
+    let other = dir.path().join("other");
+    git(
+        dir.path(),
+        &["clone", "-q", remote.to_str().unwrap(), "other"],
+    );
+    git(&other, &["config", "user.email", "other@example.com"]);
+    git(&other, &["config", "user.name", "Other"]);
+    git(
+        &other,
+        &["commit", "-q", "--allow-empty", "-m", "fix: elsewhere"],
+    );
+    git(&other, &["push", "-q", "origin", "main"]);
+
+    let output = gitscribe(&work, &["minor", "--package", "api", "--push"]);
+    assert_eq!(output.status.code(), Some(3));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Failed to push main api@0.2.0 to origin"));
+
+    // the release stays committed and tagged locally, and the tag did not reach the remote
+    assert_eq!(git(&work, &["tag", "--list"]), "api@0.2.0");
+    assert_eq!(git(&remote, &["tag", "--list"]), "");
+}
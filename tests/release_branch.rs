@@ -0,0 +1,110 @@
+//! Release branches, created without leaving the checked out branch and listed as release lines
+mod common;
+
+use common::{config, git, gitscribe, init_repo};
+use serde_json::json;
+
+/// A repo on `main` with one feature committed, releasing minor versions on their own branch
+fn setup() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let work = dir.path();
+    init_repo(work, &release_config("1.0.0"));
+    git(work, &["add", "."]);
+    git(work, &["commit", "-q", "-m", "feat: first feature"]);
+    dir
+}
+
+/// A config at the version releasing minor versions on their own branch
+fn release_config(version: &str) -> String {
+    config(json!({
+        "version": version, "commands_that_release": ["minor"], "branch_for_release": true,
+        "packages": [{"name": "api", "version": "0.1.0", "paths": ["api"]}]
+    }))
+}
+
+fn write_config(work: &std::path::Path, version: &str) {
+    std::fs::write(work.join("gitscribe.json"), release_config(version)).unwrap();
+}
+
+#[test]
+fn test_release_branch_leaves_the_checkout() {
+    let dir = setup();
+    let work = dir.path();
+    let output = gitscribe(work, &["minor"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+
+    assert_eq!(
+        git(work, &["rev-parse", "release/1.1.X"]),
+        git(work, &["rev-parse", "HEAD"])
+    );
+    assert_eq!(git(work, &["rev-parse", "--abbrev-ref", "HEAD"]), "main");
+    assert_eq!(
+        git(work, &["status", "--porcelain", "--untracked-files=all"]),
+        ""
+    );
+}
+
+#[test]
+fn test_package_release_on_a_release_branch_is_patch_only() {
+    let dir = setup();
+    let work = dir.path();
+    git(work, &["checkout", "-q", "-b", "release/1.0.X"]);
+    let head = git(work, &["rev-parse", "HEAD"]);
+
+    let output = gitscribe(work, &["minor", "--package", "api"]);
+    assert_eq!(output.status.code(), Some(10));
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("release/1.0.X is a release branch, only patch releases can be made on it"));
+    assert_eq!(git(work, &["rev-parse", "HEAD"]), head);
+
+    let output = gitscribe(work, &["patch", "--package", "api"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}
+
+#[test]
+fn test_release_lines() {
+    let dir = setup();
+    let work = dir.path();
+    // a line only on the remote
+    write_config(work, "1.0.5");
+    git(work, &["commit", "-q", "-am", "fix: first fix"]);
+    git(
+        work,
+        &["update-ref", "refs/remotes/origin/release/1.0.X", "HEAD"],
+    );
+    // a line both local and on the remote, where the local branch is ahead
+    write_config(work, "1.1.2");
+    git(work, &["commit", "-q", "-am", "fix: second fix"]);
+    git(work, &["branch", "release/1.1.X"]);
+    git(
+        work,
+        &["update-ref", "refs/remotes/origin/release/1.1.X", "HEAD~1"],
+    );
+    // a line without a config file
+    git(work, &["rm", "-q", "gitscribe.json"]);
+    git(work, &["commit", "-q", "-m", "chore: drop the config"]);
+    git(work, &["branch", "release/2.0.X"]);
+    git(work, &["reset", "-q", "--hard", "HEAD~1"]);
+    git(work, &["branch", "feature/1.2.X"]);
+
+    let output = gitscribe(work, &["branches"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines = stdout
+        .lines()
+        .skip_while(|line| !line.contains("Release lines:"))
+        .skip(1)
+        .collect::<Vec<&str>>();
+    assert_eq!(lines.len(), 3, "{stdout}");
+    assert!(lines[0].contains("release/1.1.X") && lines[0].contains("1.1.2"));
+    assert!(lines[1].contains("release/1.0.X") && lines[1].contains("1.0.5"));
+    assert!(lines[2].contains("release/2.0.X") && lines[2].contains("(no gitscribe.json)"));
+}